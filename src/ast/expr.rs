@@ -1,7 +1,20 @@
-use crate::frontend::Token;
+use std::{cell::Cell, rc::Rc};
+
+use crate::ast::{FunctionDecl, Stmt};
+use crate::frontend::{Span, Token};
+
+/// How many scopes away from the innermost one a variable is defined. This is filled in by the
+/// resolver and `None` means the variable is global.
+pub type Depth = Cell<Option<usize>>;
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone)]
-pub enum Expr {
+pub enum ExprKind {
     Unary {
         operator: Token,
         right: Box<Expr>,
@@ -14,16 +27,63 @@ pub enum Expr {
     Grouping(Box<Expr>),
     Variable {
         name: Token,
+        depth: Depth,
     },
     Assignment {
         name: Token,
         value: Box<Expr>,
+        depth: Depth,
     },
     Logical {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
+    Conditional {
+        condition: Box<Expr>,
+        when_true: Box<Expr>,
+        when_false: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+    },
+    Function(Rc<FunctionDecl>),
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    List(Vec<Expr>),
+    /// `"a ${b} c"` as its parts in source order -e.g. `"a "`, `b` and `" c"`. Each part is turned
+    /// into a string, whatever its type, and the parts are joined.
+    Interpolation(Vec<Expr>),
+    /// Key and value pairs in source order
+    Map(Vec<(Expr, Expr)>),
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    This {
+        keyword: Token,
+        depth: Depth,
+    },
+    /// `depth` is where `super` is defined. `this` is always one scope closer.
+    Super {
+        keyword: Token,
+        method: Token,
+        depth: Depth,
+    },
 
     // Treat literals as individual expressions
     BoolLiteral(bool),
@@ -34,72 +94,295 @@ pub enum Expr {
 
 impl std::fmt::Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Expr::Unary { operator, right } => write!(f, "Unary: {operator}{right}"),
-            Expr::Binary {
+        match &self.kind {
+            ExprKind::Unary { operator, right } => write!(f, "Unary: {operator}{right}"),
+            ExprKind::Binary {
                 left,
                 operator,
                 right,
             } => write!(f, "Binary: {left} {operator} {right}"),
-            Expr::Grouping(expr) => write!(f, "Grouping: ({expr})"),
-            Expr::Variable { name } => write!(f, "Variable: {name}"),
-            Expr::Assignment { name, value } => write!(f, "Assignment: {name} = {value}"),
-            Expr::Logical {
+            ExprKind::Grouping(expr) => write!(f, "Grouping: ({expr})"),
+            ExprKind::Variable { name, .. } => write!(f, "Variable: {name}"),
+            ExprKind::Assignment { name, value, .. } => write!(f, "Assignment: {name} = {value}"),
+            ExprKind::Logical {
                 left,
                 operator,
                 right,
             } => write!(f, "Binary: {left} {operator} {right}"),
-            Expr::BoolLiteral(v) => write!(f, "BoolLiteral: {v}"),
-            Expr::NumberLiteral(v) => write!(f, "NumberLiteral: {v}"),
-            Expr::StringLiteral(v) => write!(f, "StringLiteral: {v}"),
-            Expr::Nil => write!(f, "nil"),
+            ExprKind::Conditional {
+                condition,
+                when_true,
+                when_false,
+            } => write!(f, "Conditional: {condition} ? {when_true} : {when_false}"),
+            ExprKind::Call {
+                callee, arguments, ..
+            } => {
+                write!(f, "Call: {callee}(")?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{argument}")?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Function(declaration) => {
+                write!(f, "Function: fun (")?;
+                for (i, param) in declaration.params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param.lexeme)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Get { object, name } => write!(f, "Get: {object}.{}", name.lexeme),
+            ExprKind::Set {
+                object,
+                name,
+                value,
+            } => write!(f, "Set: {object}.{} = {value}", name.lexeme),
+            ExprKind::List(elements) => {
+                write!(f, "List: [")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
+            ExprKind::Interpolation(parts) => {
+                write!(f, "Interpolation: ")?;
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{part}")?;
+                }
+                Ok(())
+            }
+            ExprKind::Map(entries) => {
+                write!(f, "Map: {{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "}}")
+            }
+            ExprKind::Index { object, index } => write!(f, "Index: {object}[{index}]"),
+            ExprKind::SetIndex {
+                object,
+                index,
+                value,
+            } => write!(f, "SetIndex: {object}[{index}] = {value}"),
+            ExprKind::This { .. } => write!(f, "this"),
+            ExprKind::Super { method, .. } => write!(f, "Super: super.{}", method.lexeme),
+            ExprKind::BoolLiteral(v) => write!(f, "BoolLiteral: {v}"),
+            ExprKind::NumberLiteral(v) => write!(f, "NumberLiteral: {v}"),
+            ExprKind::StringLiteral(v) => write!(f, "StringLiteral: {v}"),
+            ExprKind::Nil => write!(f, "nil"),
         }
     }
 }
 
 impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Expr {
+        Self { kind, span }
+    }
+
     pub fn new_unary(operator: Token, right: Expr) -> Expr {
-        Self::Unary {
-            operator,
-            right: Box::new(right),
-        }
+        let span = operator.span.to(right.span);
+        Self::new(
+            ExprKind::Unary {
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        )
     }
 
     pub fn new_binary(left: Expr, operator: Token, right: Expr) -> Expr {
-        Self::Binary {
-            left: Box::new(left),
-            operator,
-            right: Box::new(right),
-        }
+        let span = left.span.to(right.span);
+        Self::new(
+            ExprKind::Binary {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        )
+    }
+
+    /// `span` includes the parentheses
+    pub fn new_grouping(expr: Expr, span: Span) -> Expr {
+        Self::new(ExprKind::Grouping(Box::new(expr)), span)
     }
 
-    pub fn new_grouping(expr: Expr) -> Expr {
-        Self::Grouping(Box::new(expr))
+    pub fn new_variable(name: Token) -> Expr {
+        let span = name.span;
+        Self::new(
+            ExprKind::Variable {
+                name,
+                depth: Depth::default(),
+            },
+            span,
+        )
     }
 
     pub fn new_assignment(name: Token, value: Expr) -> Expr {
-        Self::Assignment {
-            name,
-            value: Box::new(value),
-        }
+        let span = name.span.to(value.span);
+        Self::new(
+            ExprKind::Assignment {
+                name,
+                value: Box::new(value),
+                depth: Depth::default(),
+            },
+            span,
+        )
     }
 
     pub fn new_logical(left: Expr, operator: Token, right: Expr) -> Expr {
-        Self::Logical {
-            left: Box::new(left),
-            operator,
-            right: Box::new(right),
-        }
+        let span = left.span.to(right.span);
+        Self::new(
+            ExprKind::Logical {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        )
+    }
+
+    pub fn new_conditional(condition: Expr, when_true: Expr, when_false: Expr) -> Expr {
+        let span = condition.span.to(when_false.span);
+        Self::new(
+            ExprKind::Conditional {
+                condition: Box::new(condition),
+                when_true: Box::new(when_true),
+                when_false: Box::new(when_false),
+            },
+            span,
+        )
+    }
+
+    /// `span` goes from the `fun` keyword to the end of the body
+    pub fn new_function(params: Vec<Token>, body: Vec<Stmt>, span: Span) -> Expr {
+        Self::new(
+            ExprKind::Function(Rc::new(FunctionDecl {
+                name: None,
+                params,
+                body,
+            })),
+            span,
+        )
+    }
+
+    pub fn new_get(object: Expr, name: Token) -> Expr {
+        let span = object.span.to(name.span);
+        Self::new(
+            ExprKind::Get {
+                object: Box::new(object),
+                name,
+            },
+            span,
+        )
+    }
+
+    pub fn new_set(object: Expr, name: Token, value: Expr) -> Expr {
+        let span = object.span.to(value.span);
+        Self::new(
+            ExprKind::Set {
+                object: Box::new(object),
+                name,
+                value: Box::new(value),
+            },
+            span,
+        )
+    }
+
+    pub fn new_list(elements: Vec<Expr>, span: Span) -> Expr {
+        Self::new(ExprKind::List(elements), span)
+    }
+
+    /// `span` covers the whole string, from its opening to its closing quote
+    pub fn new_interpolation(parts: Vec<Expr>, span: Span) -> Expr {
+        Self::new(ExprKind::Interpolation(parts), span)
+    }
+
+    pub fn new_map(entries: Vec<(Expr, Expr)>, span: Span) -> Expr {
+        Self::new(ExprKind::Map(entries), span)
+    }
+
+    /// `span` ends at the closing bracket, which is not part of the index
+    pub fn new_index(object: Expr, index: Expr, span: Span) -> Expr {
+        Self::new(
+            ExprKind::Index {
+                object: Box::new(object),
+                index: Box::new(index),
+            },
+            span,
+        )
+    }
+
+    pub fn new_set_index(object: Expr, index: Expr, value: Expr) -> Expr {
+        let span = object.span.to(value.span);
+        Self::new(
+            ExprKind::SetIndex {
+                object: Box::new(object),
+                index: Box::new(index),
+                value: Box::new(value),
+            },
+            span,
+        )
+    }
+
+    pub fn new_this(keyword: Token) -> Expr {
+        let span = keyword.span;
+        Self::new(
+            ExprKind::This {
+                keyword,
+                depth: Depth::default(),
+            },
+            span,
+        )
+    }
+
+    pub fn new_super(keyword: Token, method: Token) -> Expr {
+        let span = keyword.span.to(method.span);
+        Self::new(
+            ExprKind::Super {
+                keyword,
+                method,
+                depth: Depth::default(),
+            },
+            span,
+        )
+    }
+
+    /// `paren` is the closing parenthesis
+    pub fn new_call(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Expr {
+        let span = callee.span.to(paren.span);
+        Self::new(
+            ExprKind::Call {
+                callee: Box::new(callee),
+                paren,
+                arguments,
+            },
+            span,
+        )
     }
 
     // REVIEW: this could be a trait and then there could be an AST printer
     // NOTE: I'll see how far I can get without the visitor pattern suggested in the book
     pub fn print(e: &Expr) -> String {
-        match e {
-            Expr::Unary { operator, right } => {
+        match &e.kind {
+            ExprKind::Unary { operator, right } => {
                 format!("({} {})", operator.lexeme, Expr::print(right))
             }
-            Expr::Binary {
+            ExprKind::Binary {
                 left,
                 operator,
                 right,
@@ -111,7 +394,7 @@ impl Expr {
                     Expr::print(right)
                 )
             }
-            Expr::Logical {
+            ExprKind::Logical {
                 left,
                 operator,
                 right,
@@ -123,16 +406,101 @@ impl Expr {
                     Expr::print(right)
                 )
             }
-            Expr::Assignment { name, value } => format!("{} {}", name.lexeme, Expr::print(value)),
+            ExprKind::Assignment { name, value, .. } => {
+                format!("{} {}", name.lexeme, Expr::print(value))
+            }
+            ExprKind::Conditional {
+                condition,
+                when_true,
+                when_false,
+            } => format!(
+                "(? {} {} {})",
+                Expr::print(condition),
+                Expr::print(when_true),
+                Expr::print(when_false)
+            ),
+            ExprKind::Call {
+                callee, arguments, ..
+            } => {
+                let mut result = format!("(call {}", Expr::print(callee));
+                for argument in arguments {
+                    result.push(' ');
+                    result.push_str(&Expr::print(argument));
+                }
+                result.push(')');
+                result
+            }
+            ExprKind::Function(declaration) => {
+                let params: Vec<&str> = declaration
+                    .params
+                    .iter()
+                    .map(|param| param.lexeme.as_str())
+                    .collect();
+                format!("(fun ({}))", params.join(" "))
+            }
+            ExprKind::Get { object, name } => {
+                format!("(. {} {})", Expr::print(object), name.lexeme)
+            }
+            ExprKind::Set {
+                object,
+                name,
+                value,
+            } => format!(
+                "(= {} {} {})",
+                Expr::print(object),
+                name.lexeme,
+                Expr::print(value)
+            ),
+            ExprKind::List(elements) => {
+                let mut result = "(list".to_string();
+                for element in elements {
+                    result.push(' ');
+                    result.push_str(&Expr::print(element));
+                }
+                result.push(')');
+                result
+            }
+            ExprKind::Interpolation(parts) => {
+                let mut result = "(interpolate".to_string();
+                for part in parts {
+                    result.push(' ');
+                    result.push_str(&Expr::print(part));
+                }
+                result.push(')');
+                result
+            }
+            ExprKind::Map(entries) => {
+                let mut result = "(map".to_string();
+                for (key, value) in entries {
+                    result.push_str(&format!(" ({} {})", Expr::print(key), Expr::print(value)));
+                }
+                result.push(')');
+                result
+            }
+            ExprKind::Index { object, index } => {
+                format!("([] {} {})", Expr::print(object), Expr::print(index))
+            }
+            ExprKind::SetIndex {
+                object,
+                index,
+                value,
+            } => format!(
+                "([]= {} {} {})",
+                Expr::print(object),
+                Expr::print(index),
+                Expr::print(value)
+            ),
+            ExprKind::This { .. } => "this".to_string(),
+            ExprKind::Super { method, .. } => format!("(super {})", method.lexeme),
 
-            Expr::StringLiteral(value) => value.clone(),
-            Expr::NumberLiteral(value) => value.to_string(),
-            Expr::BoolLiteral(value) => value.to_string(),
-            Expr::Nil => "nil".to_string(),
-            Expr::Grouping(e) => {
+            ExprKind::StringLiteral(value) => value.clone(),
+            ExprKind::NumberLiteral(value) => value.to_string(),
+            ExprKind::BoolLiteral(value) => value.to_string(),
+            ExprKind::Nil => "nil".to_string(),
+            ExprKind::Grouping(e) => {
                 format!("(group {})", Expr::print(e))
             }
-            Expr::Variable { name } => name.lexeme.to_string(),
+            ExprKind::Variable { name, .. } => name.lexeme.to_string(),
         }
     }
 }
@@ -142,10 +510,18 @@ mod tests {
     use super::*;
     use crate::frontend::{Span, Token, TokenType};
 
+    fn number(value: f64) -> Expr {
+        Expr::new(ExprKind::NumberLiteral(value), Span::default())
+    }
+
+    fn boolean(value: bool) -> Expr {
+        Expr::new(ExprKind::BoolLiteral(value), Span::default())
+    }
+
     #[test]
     fn unary() {
-        let operator = Token::new(TokenType::Minus, String::from("-"), Span::new(1, 1));
-        let literal = Expr::NumberLiteral(1.0);
+        let operator = Token::new(TokenType::Minus, String::from("-"), Span::default());
+        let literal = number(1.0);
         let e = Expr::new_unary(operator, literal);
         let result = Expr::print(&e);
         assert_eq!(result, "(- 1)")
@@ -153,8 +529,8 @@ mod tests {
 
     #[test]
     fn binary() {
-        let operator = Token::new(TokenType::Minus, String::from("-"), Span::new(1, 1));
-        let literal = Expr::NumberLiteral(1.0);
+        let operator = Token::new(TokenType::Minus, String::from("-"), Span::default());
+        let literal = number(1.0);
         let e = Expr::new_binary(literal.clone(), operator, literal);
         let result = Expr::print(&e);
         assert_eq!(result, "(- 1 1)")
@@ -162,15 +538,15 @@ mod tests {
 
     #[test]
     fn literal() {
-        let literal = Expr::NumberLiteral(1.0);
+        let literal = number(1.0);
         let result = Expr::print(&literal);
         assert_eq!(result, "1")
     }
 
     #[test]
     fn grouping() {
-        let literal = Expr::NumberLiteral(1.0);
-        let e = Expr::new_grouping(literal);
+        let literal = number(1.0);
+        let e = Expr::new_grouping(literal, Span::default());
         let result = Expr::print(&e);
         assert_eq!(result, "(group 1)")
     }
@@ -178,15 +554,41 @@ mod tests {
     #[test]
     fn nested() {
         let left = Expr::new_unary(
-            Token::new(TokenType::Minus, "-".to_string(), Span::new(1, 1)),
-            Expr::NumberLiteral(123.0),
+            Token::new(TokenType::Minus, "-".to_string(), Span::default()),
+            number(123.0),
         );
-        let right = Expr::new_grouping(Expr::NumberLiteral(45.67));
+        let right = Expr::new_grouping(number(45.67), Span::default());
 
-        let operator = Token::new(TokenType::Star, "*".to_string(), Span::new(1, 1));
+        let operator = Token::new(TokenType::Star, "*".to_string(), Span::default());
 
         let e = Expr::new_binary(left, operator, right);
         let result = Expr::print(&e);
         assert_eq!(result, "(* (- 123) (group 45.67))")
     }
+
+    #[test]
+    fn conditional() {
+        let e = Expr::new_conditional(
+            boolean(true),
+            number(1.0),
+            Expr::new_conditional(boolean(false), number(2.0), number(3.0)),
+        );
+        let result = Expr::print(&e);
+        assert_eq!(result, "(? true 1 (? false 2 3))")
+    }
+
+    #[test]
+    fn call() {
+        let callee = Expr::new_variable(Token::new(
+            TokenType::Identifier,
+            "add".to_string(),
+            Span::default(),
+        ));
+        let paren = Token::new(TokenType::RightParen, ")".to_string(), Span::default());
+        let arguments = vec![number(1.0), number(2.0)];
+
+        let e = Expr::new_call(callee, paren, arguments);
+        let result = Expr::print(&e);
+        assert_eq!(result, "(call add 1 2)")
+    }
 }
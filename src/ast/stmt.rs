@@ -1,8 +1,16 @@
-use crate::ast::Expr;
-use crate::frontend::Token;
+use std::rc::Rc;
+
+use crate::ast::{Depth, Expr};
+use crate::frontend::{Span, Token};
+
+#[derive(Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
 
 #[derive(Debug)]
-pub enum Stmt {
+pub enum StmtKind {
     Block(Vec<Stmt>),
     Expression(Expr),
     Print(Expr),
@@ -18,31 +26,136 @@ pub enum Stmt {
     While {
         condition: Expr,
         body: Box<Stmt>,
+        label: Option<Token>,
+    },
+    /// The body runs once before the condition is first checked
+    DoWhile {
+        body: Box<Stmt>,
+        condition: Expr,
+        label: Option<Token>,
+    },
+    /// Runs until a `break` or `return` leaves it
+    Loop {
+        body: Box<Stmt>,
+        label: Option<Token>,
+    },
+    /// `depth` is the number of loops between the statement and the loop it continues, as
+    /// recorded by the resolver
+    Continue {
+        keyword: Token,
+        label: Option<Token>,
+        depth: Depth,
+    },
+    /// `depth` is the number of loops between the statement and the loop it breaks out of, as
+    /// recorded by the resolver
+    Break {
+        keyword: Token,
+        label: Option<Token>,
+        depth: Depth,
     },
-    Continue,
-    Break,
     For {
         initializer: Option<Box<Stmt>>,
         condition: Option<Expr>,
         increment: Option<Expr>,
         body: Box<Stmt>,
+        label: Option<Token>,
+    },
+    Function(Rc<FunctionDecl>),
+    Class {
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Rc<FunctionDecl>>,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
     },
 }
 
+/// Function declarations are shared with every function value created from them, so they need to
+/// outlive the statements they were parsed into -e.g. a function defined in one line of the REPL
+/// and called in the next.
+#[derive(Debug)]
+pub struct FunctionDecl {
+    /// Anonymous functions have no name
+    pub name: Option<Token>,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
+
 impl Stmt {
-    pub fn new_conditional(condition: Expr, when_true: Stmt, when_false: Option<Stmt>) -> Self {
-        Self::Conditional {
-            condition,
-            when_true: Box::new(when_true),
-            when_false: when_false.map(Box::new),
-        }
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
     }
 
-    pub fn new_while(condition: Expr, body: Stmt) -> Self {
-        Self::While {
-            condition,
-            body: Box::new(body),
-        }
+    pub fn new_conditional(
+        condition: Expr,
+        when_true: Stmt,
+        when_false: Option<Stmt>,
+        span: Span,
+    ) -> Self {
+        Self::new(
+            StmtKind::Conditional {
+                condition,
+                when_true: Box::new(when_true),
+                when_false: when_false.map(Box::new),
+            },
+            span,
+        )
+    }
+
+    pub fn new_while(condition: Expr, body: Stmt, label: Option<Token>, span: Span) -> Self {
+        Self::new(
+            StmtKind::While {
+                condition,
+                body: Box::new(body),
+                label,
+            },
+            span,
+        )
+    }
+
+    pub fn new_do_while(body: Stmt, condition: Expr, label: Option<Token>, span: Span) -> Self {
+        Self::new(
+            StmtKind::DoWhile {
+                body: Box::new(body),
+                condition,
+                label,
+            },
+            span,
+        )
+    }
+
+    pub fn new_loop(body: Stmt, label: Option<Token>, span: Span) -> Self {
+        Self::new(
+            StmtKind::Loop {
+                body: Box::new(body),
+                label,
+            },
+            span,
+        )
+    }
+
+    pub fn new_break(keyword: Token, label: Option<Token>, span: Span) -> Self {
+        Self::new(
+            StmtKind::Break {
+                keyword,
+                label,
+                depth: Depth::default(),
+            },
+            span,
+        )
+    }
+
+    pub fn new_continue(keyword: Token, label: Option<Token>, span: Span) -> Self {
+        Self::new(
+            StmtKind::Continue {
+                keyword,
+                label,
+                depth: Depth::default(),
+            },
+            span,
+        )
     }
 
     pub fn new_for(
@@ -50,12 +163,173 @@ impl Stmt {
         condition: Option<Expr>,
         increment: Option<Expr>,
         body: Stmt,
+        label: Option<Token>,
+        span: Span,
     ) -> Self {
-        Self::For {
-            initializer: initializer.map(Box::new),
-            condition,
-            increment,
-            body: Box::new(body),
+        Self::new(
+            StmtKind::For {
+                initializer: initializer.map(Box::new),
+                condition,
+                increment,
+                body: Box::new(body),
+                label,
+            },
+            span,
+        )
+    }
+
+    /// Print a statement in the same style as `Expr::print`. `_` stands for the optional parts of
+    /// a `for` that were left out.
+    pub fn print(s: &Stmt) -> String {
+        match &s.kind {
+            StmtKind::Block(stmts) => Self::print_list("block", stmts),
+            StmtKind::Expression(expr) => format!("(; {})", Expr::print(expr)),
+            StmtKind::Print(expr) => format!("(print {})", Expr::print(expr)),
+            StmtKind::Var { name, initializer } => match initializer {
+                Some(initializer) => format!("(var {} {})", name.lexeme, Expr::print(initializer)),
+                None => format!("(var {})", name.lexeme),
+            },
+            StmtKind::Conditional {
+                condition,
+                when_true,
+                when_false,
+            } => match when_false {
+                Some(when_false) => format!(
+                    "(if {} {} {})",
+                    Expr::print(condition),
+                    Stmt::print(when_true),
+                    Stmt::print(when_false)
+                ),
+                None => format!("(if {} {})", Expr::print(condition), Stmt::print(when_true)),
+            },
+            StmtKind::While {
+                condition,
+                body,
+                label,
+            } => Self::labelled(
+                label,
+                format!("(while {} {})", Expr::print(condition), Stmt::print(body)),
+            ),
+            StmtKind::DoWhile {
+                body,
+                condition,
+                label,
+            } => Self::labelled(
+                label,
+                format!("(do {} {})", Stmt::print(body), Expr::print(condition)),
+            ),
+            StmtKind::Loop { body, label } => {
+                Self::labelled(label, format!("(loop {})", Stmt::print(body)))
+            }
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                label,
+            } => Self::labelled(
+                label,
+                format!(
+                    "(for {} {} {} {})",
+                    initializer.as_deref().map_or("_".to_string(), Stmt::print),
+                    condition.as_ref().map_or("_".to_string(), Expr::print),
+                    increment.as_ref().map_or("_".to_string(), Expr::print),
+                    Stmt::print(body)
+                ),
+            ),
+            StmtKind::Continue { keyword, label, .. } | StmtKind::Break { keyword, label, .. } => {
+                match label {
+                    Some(label) => format!("({} {})", keyword.lexeme, label.lexeme),
+                    None => format!("({})", keyword.lexeme),
+                }
+            }
+            StmtKind::Function(declaration) => Self::print_function(declaration),
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let mut result = format!("(class {}", name.lexeme);
+                if let Some(superclass) = superclass {
+                    result.push_str(&format!(" < {}", Expr::print(superclass)));
+                }
+                for method in methods {
+                    result.push(' ');
+                    result.push_str(&Self::print_function(method));
+                }
+                result.push(')');
+                result
+            }
+            StmtKind::Return { value, .. } => match value {
+                Some(value) => format!("(return {})", Expr::print(value)),
+                None => "(return)".to_string(),
+            },
+        }
+    }
+
+    fn print_function(declaration: &FunctionDecl) -> String {
+        let params: Vec<&str> = declaration
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        let name = declaration
+            .name
+            .as_ref()
+            .map_or("", |name| name.lexeme.as_str());
+
+        Self::print_list(
+            &format!("fun {name} ({})", params.join(" ")),
+            &declaration.body,
+        )
+    }
+
+    fn print_list(head: &str, stmts: &[Stmt]) -> String {
+        let mut result = format!("({head}");
+        for stmt in stmts {
+            result.push(' ');
+            result.push_str(&Stmt::print(stmt));
+        }
+        result.push(')');
+        result
+    }
+
+    fn labelled(label: &Option<Token>, loop_stmt: String) -> String {
+        match label {
+            Some(label) => format!("(label {} {loop_stmt})", label.lexeme),
+            None => loop_stmt,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LoxConfig,
+        frontend::{FileId, Parser, Scanner},
+    };
+
+    fn print(src: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(src, FileId::default(), LoxConfig::default());
+        let (tokens, _) = scanner.scan_tokens();
+        let stmts = Parser::new(tokens).parse().expect("valid syntax");
+        stmts.iter().map(Stmt::print).collect()
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            print("var a = 1; if (a) print a; else { a = 2; }"),
+            ["(var a 1)", "(if a (print a) (block (; a 2)))"]
+        );
+        assert_eq!(
+            print("outer: for (var i = 0; i < 3;) break outer;"),
+            ["(label outer (for (var i 0) (< i 3) _ (break outer)))"]
+        );
+        assert_eq!(
+            print("class B < A { f(x) { return x; } }"),
+            ["(class B < A (fun f (x) (return x)))"]
+        );
+    }
+}
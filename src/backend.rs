@@ -1,10 +1,20 @@
+mod class;
+mod completion;
 mod environment;
+mod function;
 mod interpreter;
+mod list;
+mod map;
 mod runtime_error;
 mod value;
 
 // re-export as a flat package
+pub use class::*;
+pub use completion::*;
 pub use environment::*;
+pub use function::*;
 pub use interpreter::*;
+pub use list::*;
+pub use map::*;
 pub use runtime_error::*;
 pub use value::*;
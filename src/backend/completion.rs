@@ -0,0 +1,20 @@
+use super::Value;
+
+/// How a statement finished executing
+///
+/// `break`, `continue` and `return` unwind through the enclosing statements until a loop or a
+/// function call consumes them. They are not errors, so they travel separately from
+/// `RuntimeError`.
+#[derive(Debug)]
+pub enum Completion {
+    Normal,
+
+    /// Leave a loop. Holds the number of enclosing loops to leave first, 0 for the innermost one.
+    Break(usize),
+
+    /// Skip to the next iteration of a loop. Holds the number of enclosing loops to leave first,
+    /// 0 for the innermost one.
+    Continue(usize),
+
+    Return(Value),
+}
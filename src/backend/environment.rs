@@ -1,65 +1,159 @@
 use super::{EnvironmentError, Value};
-use std::collections::HashMap;
-
-pub type Scope = HashMap<String, Value>;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 pub type EnvResult<T> = Result<T, EnvironmentError>;
 
 #[derive(Debug, Default)]
+pub struct Scope {
+    /// Variables declared without an initializer have no value until they are assigned one
+    values: HashMap<String, Option<Value>>,
+    enclosing: Option<Env>,
+}
+
+/// A handle to the innermost scope of a scope chain
+///
+/// Scopes are reference counted so that closures can hold on to the chain they were created in
+/// after the interpreter has left it. Cloning an `Env` is cheap and shares the same scopes.
+#[derive(Debug, Default, Clone)]
 pub struct Env {
-    scopes: Vec<Scope>,
+    scope: Rc<RefCell<Scope>>,
 }
 
 impl Env {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new scope whose parent is `enclosing`
+    pub fn new_enclosed(enclosing: &Env) -> Self {
         Self {
-            scopes: vec![HashMap::new()],
+            scope: Rc::new(RefCell::new(Scope {
+                values: HashMap::new(),
+                enclosing: Some(enclosing.clone()),
+            })),
         }
     }
 
-    pub fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+    pub fn define(&mut self, name: &str, value: &Value) {
+        self.scope
+            .borrow_mut()
+            .values
+            .insert(name.to_string(), Some(value.clone()));
     }
 
-    pub fn end_scope(&mut self) {
-        // do not allow global scope to be dropped
-        if self.scopes.len() > 1 {
-            self.scopes.pop();
-        }
+    /// Define a variable that can't be read until it is assigned
+    pub fn declare(&mut self, name: &str) {
+        self.scope
+            .borrow_mut()
+            .values
+            .insert(name.to_string(), None);
     }
 
-    fn last_scope(&mut self) -> &mut Scope {
-        self.scopes
-            .last_mut()
-            .expect("Scope is undefined. This should never happen")
-    }
+    pub fn assign(&mut self, name: &str, value: &Value) -> EnvResult<()> {
+        let env = self
+            .find(name)
+            .ok_or_else(|| EnvironmentError::UndefinedVariable {
+                name: name.to_string(),
+            })?;
 
-    pub fn define(&mut self, name: &str, value: &Value) {
-        self.last_scope().insert(name.to_string(), value.clone());
+        env.scope
+            .borrow_mut()
+            .values
+            .insert(name.to_string(), Some(value.clone()));
+
+        Ok(())
     }
 
-    pub fn assign(&mut self, name: &str, value: &Value) -> EnvResult<()> {
-        for scope in self.scopes.iter_mut().rev() {
-            if scope.contains_key(name) {
-                scope.insert(name.to_string(), value.clone());
-                return Ok(());
-            }
-        }
+    pub fn get(&self, name: &str) -> EnvResult<Value> {
+        let env = self
+            .find(name)
+            .ok_or_else(|| EnvironmentError::UndefinedVariable {
+                name: name.to_string(),
+            })?;
 
-        Err(EnvironmentError::UndefinedVariable {
+        let value = env.scope.borrow().values[name].clone();
+        value.ok_or_else(|| EnvironmentError::UninitializedVariable {
             name: name.to_string(),
         })
     }
 
-    pub fn get(&self, name: &str) -> EnvResult<&Value> {
-        for scope in self.scopes.iter().rev() {
-            if let Some(value) = scope.get(name) {
-                return Ok(value);
+    /// Get a variable from the scope `depth` levels up the chain, as recorded by the resolver
+    pub fn get_at(&self, depth: usize, name: &str) -> EnvResult<Value> {
+        let env = self.ancestor(depth);
+        let value = env.scope.borrow().values.get(name).cloned();
+
+        match value {
+            Some(Some(value)) => Ok(value),
+            Some(None) => Err(EnvironmentError::UninitializedVariable {
+                name: name.to_string(),
+            }),
+            None => Err(EnvironmentError::UndefinedVariable {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    pub fn assign_at(&mut self, depth: usize, name: &str, value: &Value) -> EnvResult<()> {
+        let env = self.ancestor(depth);
+        let mut scope = env.scope.borrow_mut();
+
+        match scope.values.get_mut(name) {
+            Some(slot) => {
+                *slot = Some(value.clone());
+                Ok(())
             }
+            None => Err(EnvironmentError::UndefinedVariable {
+                name: name.to_string(),
+            }),
         }
+    }
 
-        Err(EnvironmentError::UndefinedVariable {
-            name: name.to_string(),
-        })
+    fn ancestor(&self, depth: usize) -> Env {
+        let mut env = self.clone();
+        for _ in 0..depth {
+            let enclosing = env
+                .scope
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("Resolved depth is out of range. This should never happen");
+            env = enclosing;
+        }
+
+        env
+    }
+
+    /// Every variable in the scope chain, innermost scope first and sorted by name. Variables that
+    /// have been declared but not assigned have no value.
+    pub fn scopes(&self) -> Vec<Vec<(String, Option<Value>)>> {
+        let mut scopes = Vec::new();
+        let mut env = Some(self.clone());
+        while let Some(current) = env {
+            let scope = current.scope.borrow();
+            let mut values: Vec<_> = scope
+                .values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            values.sort_by(|a, b| a.0.cmp(&b.0));
+
+            scopes.push(values);
+            env = scope.enclosing.clone();
+        }
+
+        scopes
+    }
+
+    /// Walk up the scope chain until a scope that defines `name` is found
+    fn find(&self, name: &str) -> Option<Env> {
+        let mut env = self.clone();
+        loop {
+            if env.scope.borrow().values.contains_key(name) {
+                return Some(env);
+            }
+
+            let enclosing = env.scope.borrow().enclosing.clone()?;
+            env = enclosing;
+        }
     }
 }
@@ -0,0 +1,74 @@
+use std::{fmt, rc::Rc};
+
+use super::{Env, Instance, Value};
+use crate::ast::FunctionDecl;
+
+/// A user-defined function created from a `fun` declaration or an anonymous `fun` expression
+#[derive(Debug)]
+pub struct Function {
+    declaration: Rc<FunctionDecl>,
+
+    /// The scope chain the function was created in
+    closure: Env,
+
+    /// Initializers always return `this`
+    is_initializer: bool,
+}
+
+impl Function {
+    pub fn new(declaration: Rc<FunctionDecl>, closure: Env) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer: false,
+        }
+    }
+
+    pub fn new_method(declaration: Rc<FunctionDecl>, closure: Env, is_initializer: bool) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Create a copy of this method with `this` bound to `instance`
+    pub fn bind(&self, instance: Rc<Instance>) -> Function {
+        let mut closure = Env::new_enclosed(&self.closure);
+        closure.define("this", &Value::Instance(instance));
+
+        Self::new_method(Rc::clone(&self.declaration), closure, self.is_initializer)
+    }
+
+    pub fn is_initializer(&self) -> bool {
+        self.is_initializer
+    }
+
+    pub fn declaration(&self) -> &FunctionDecl {
+        &self.declaration
+    }
+
+    pub fn closure(&self) -> &Env {
+        &self.closure
+    }
+
+    pub fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+}
+
+// Functions are only ever equal to themselves
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.declaration.name {
+            Some(name) => write!(f, "<fn {}>", name.lexeme),
+            None => write!(f, "<fn>"),
+        }
+    }
+}
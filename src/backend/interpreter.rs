@@ -1,137 +1,317 @@
 use super::*;
 use crate::{
-    ast::{Expr, Stmt},
-    frontend::TokenType,
+    LoxConfig,
+    ast::{Depth, Expr, ExprKind, FunctionDecl, Stmt, StmtKind},
+    frontend::{Span, Token, TokenType},
 };
-use std::slice;
+use std::{ops::ControlFlow, rc::Rc};
 
 pub type InterpreterResult<T> = Result<T, RuntimeError>;
 
-#[derive(Debug, Default)]
+/// Stack size, in bytes, that the thread running the interpreter needs. Deeper nesting is a
+/// `StackOverflow` error instead of overflowing the native stack.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Upper bound on the native stack taken by one nested `evaluate` or `execute`, including the
+/// frames of a call in between. Measured at under 28KB in debug builds.
+const FRAME_SIZE: usize = 32 * 1024;
+
+/// How deep expressions and statements can nest, across calls, before a `StackOverflow` error
+const MAX_DEPTH: usize = STACK_SIZE / FRAME_SIZE;
+
+#[derive(Debug)]
 pub struct Interpreter {
     env: Env,
+
+    /// Unresolved variables are looked up here
+    globals: Env,
+
+    config: LoxConfig,
+
+    /// Number of `evaluate` and `execute` calls that haven't returned yet
+    depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new(LoxConfig::default())
+    }
 }
 
 impl Interpreter {
-    pub fn new() -> Self {
-        Self { env: Env::new() }
+    pub fn new(config: LoxConfig) -> Self {
+        let globals = Env::new();
+        Self {
+            env: globals.clone(),
+            globals,
+            config,
+            depth: 0,
+        }
     }
 
     pub fn interpret(&mut self, stmts: &[Stmt]) -> InterpreterResult<()> {
-        for stmt in stmts.iter() {
-            match stmt {
-                Stmt::Print(expr) => {
-                    let result = self.evaluate(expr)?;
-                    println!("{result}");
-                }
-                Stmt::Expression(expr) => _ = self.evaluate(expr)?,
-                Stmt::Var { name, initializer } => {
-                    let value = match initializer {
-                        Some(expr) => self.evaluate(expr)?,
-                        None => Value::Nil,
-                    };
+        // the resolver rejects `break`, `continue` and `return` outside of loops and functions, so
+        // top-level code always completes normally
+        self.execute_all(stmts)?;
+        Ok(())
+    }
+
+    /// Like `interpret`, but returns the value of the last statement when it is an expression
+    /// statement -e.g. so that the REPL can echo it
+    pub fn interpret_with_value(&mut self, stmts: &[Stmt]) -> InterpreterResult<Option<Value>> {
+        match stmts.split_last() {
+            Some((last, rest)) if let StmtKind::Expression(expr) = &last.kind => {
+                self.interpret(rest)?;
+                self.evaluate(expr).map(Some)
+            }
+            _ => self.interpret(stmts).map(|()| None),
+        }
+    }
+
+    /// Every variable that is currently in scope, see `Env::scopes`
+    pub fn scopes(&self) -> Vec<Vec<(String, Option<Value>)>> {
+        self.env.scopes()
+    }
+
+    fn execute_all(&mut self, stmts: &[Stmt]) -> InterpreterResult<Completion> {
+        for stmt in stmts {
+            match self.execute(stmt)? {
+                Completion::Normal => {}
+                completion => return Ok(completion),
+            }
+        }
+
+        Ok(Completion::Normal)
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> InterpreterResult<Completion> {
+        self.enter(stmt.span)?;
+        let completion = self.execute_kind(stmt);
+        self.depth -= 1;
+        completion
+    }
+
+    fn execute_kind(&mut self, stmt: &Stmt) -> InterpreterResult<Completion> {
+        match &stmt.kind {
+            StmtKind::Print(expr) => {
+                let result = self.evaluate(expr)?;
+                println!("{result}");
+            }
+            StmtKind::Expression(expr) => _ = self.evaluate(expr)?,
+            StmtKind::Var { name, initializer } => match initializer {
+                Some(expr) => {
+                    let value = self.evaluate(expr)?;
                     self.env.define(&name.lexeme, &value);
                 }
-                Stmt::Block(stmts) => {
-                    self.env.begin_scope();
-                    self.interpret(stmts)?;
-                    self.env.end_scope();
-                }
-                Stmt::Conditional {
-                    condition,
-                    when_true,
-                    when_false,
-                } => {
-                    if self.evaluate(condition)?.is_truthy() {
-                        // else cute if branch
-                        self.interpret(slice::from_ref(when_true))?;
-                    } else if let Some(stmt) = when_false {
-                        // execute else branch if defined
-                        self.interpret(slice::from_ref(stmt))?;
-                    }
-                }
-                Stmt::While { condition, body } => {
-                    while self.evaluate(condition)?.is_truthy() {
-                        if let Err(e) = self.interpret(slice::from_ref(body)) {
-                            match e {
-                                RuntimeError::Continue => continue,
-                                RuntimeError::Break => break,
-                                _ => return Err(e),
-                            }
-                        }
+                None if self.config.strict_uninitialized => self.env.declare(&name.lexeme),
+                None => self.env.define(&name.lexeme, &Value::Nil),
+            },
+            StmtKind::Block(stmts) => {
+                let env = Env::new_enclosed(&self.env);
+                return self.with_env(env, |this| this.execute_all(stmts));
+            }
+            StmtKind::Conditional {
+                condition,
+                when_true,
+                when_false,
+            } => {
+                if self.evaluate(condition)?.is_truthy(&self.config) {
+                    return self.execute(when_true);
+                } else if let Some(stmt) = when_false {
+                    return self.execute(stmt);
+                }
+            }
+            StmtKind::While {
+                condition, body, ..
+            } => {
+                while self.evaluate(condition)?.is_truthy(&self.config) {
+                    if let ControlFlow::Break(completion) = after_body(self.execute(body)?) {
+                        return Ok(completion);
                     }
                 }
-                Stmt::For {
-                    initializer,
-                    condition,
-                    increment,
-                    body,
-                } => {
-                    // capture for loop initializer in a new scope
-                    self.env.begin_scope();
-
+            }
+            StmtKind::DoWhile {
+                body, condition, ..
+            } => loop {
+                if let ControlFlow::Break(completion) = after_body(self.execute(body)?) {
+                    return Ok(completion);
+                }
+                if !self.evaluate(condition)?.is_truthy(&self.config) {
+                    break;
+                }
+            },
+            StmtKind::Loop { body, .. } => loop {
+                if let ControlFlow::Break(completion) = after_body(self.execute(body)?) {
+                    return Ok(completion);
+                }
+            },
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                // the initializer's variables live in their own scope around the loop
+                let env = Env::new_enclosed(&self.env);
+                return self.with_env(env, |this| {
                     if let Some(initializer) = initializer {
-                        self.interpret(slice::from_ref(initializer))?;
+                        this.execute(initializer)?;
                     }
 
                     while match condition {
-                        Some(expr) => self.evaluate(expr)?.is_truthy(),
+                        Some(expr) => this.evaluate(expr)?.is_truthy(&this.config),
                         None => true,
                     } {
-                        // self.interpret(slice::from_ref(body))?;
-
-                        let result = self.interpret(slice::from_ref(body));
+                        // `continue` still runs the increment, `break` does not
+                        if let ControlFlow::Break(completion) = after_body(this.execute(body)?) {
+                            return Ok(completion);
+                        }
 
                         if let Some(increment) = increment {
-                            self.evaluate(increment)?;
+                            this.evaluate(increment)?;
                         }
+                    }
 
-                        if let Err(e) = result {
-                            match e {
-                                RuntimeError::Continue => continue,
-                                RuntimeError::Break => break,
-                                _ => return Err(e),
-                            }
+                    Ok(Completion::Normal)
+                });
+            }
+            StmtKind::Continue { depth, .. } => {
+                return Ok(Completion::Continue(depth.get().unwrap_or(0)));
+            }
+            StmtKind::Break { depth, .. } => {
+                return Ok(Completion::Break(depth.get().unwrap_or(0)));
+            }
+            StmtKind::Function(declaration) => {
+                let function = self.new_function(declaration);
+                if let Some(name) = &declaration.name {
+                    self.env.define(&name.lexeme, &function);
+                }
+            }
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let superclass = match superclass {
+                    Some(expr) => match self.evaluate(expr)? {
+                        Value::Class(class) => Some(class),
+                        value => {
+                            return Err(RuntimeError::InvalidSuperclass {
+                                value,
+                                span: expr.span,
+                            });
                         }
+                    },
+                    None => None,
+                };
+
+                // methods of a subclass close over a scope where `super` is defined
+                let env = match &superclass {
+                    Some(superclass) => {
+                        let mut env = Env::new_enclosed(&self.env);
+                        env.define("super", &Value::Class(Rc::clone(superclass)));
+                        env
                     }
-                    self.env.end_scope();
-                }
-                Stmt::Continue => return Err(RuntimeError::Continue),
-                Stmt::Break => return Err(RuntimeError::Break),
-            };
-        }
+                    None => self.env.clone(),
+                };
 
-        Ok(())
+                let methods = methods
+                    .iter()
+                    .filter_map(|method| {
+                        let name = method.name.as_ref()?.lexeme.clone();
+                        let is_initializer = name == "init";
+                        let function =
+                            Function::new_method(Rc::clone(method), env.clone(), is_initializer);
+                        Some((name, Rc::new(function)))
+                    })
+                    .collect();
+
+                let class = Class::new(name.lexeme.clone(), superclass, methods);
+                self.env.define(&name.lexeme, &Value::Class(Rc::new(class)));
+            }
+            StmtKind::Return { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                return Ok(Completion::Return(value));
+            }
+        };
+
+        Ok(Completion::Normal)
+    }
+
+    /// Run `f` with `env` as the current scope, then restore the previous scope whether `f`
+    /// completed, unwound with a `Completion` or failed
+    fn with_env<T>(&mut self, env: Env, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.env, env);
+        let result = f(self);
+        self.env = previous;
+        result
     }
 
     fn evaluate(&mut self, expr: &Expr) -> InterpreterResult<Value> {
-        match expr {
-            Expr::BoolLiteral(v) => Ok(Value::Bool(*v)),
-            Expr::StringLiteral(v) => Ok(Value::String(v.clone())),
-            Expr::NumberLiteral(v) => Ok(Value::Number(*v)),
-            Expr::Nil => Ok(Value::Nil),
-            Expr::Grouping(expr) => self.evaluate(expr),
-
-            Expr::Unary { operator, right } => {
+        self.enter(expr.span)?;
+        let value = self.evaluate_kind(expr);
+        self.depth -= 1;
+        value
+    }
+
+    /// Go one level deeper, failing before the native stack runs out, which would abort the whole
+    /// process. `span` is where the stack overflow is reported.
+    fn enter(&mut self, span: Span) -> InterpreterResult<()> {
+        if self.depth == MAX_DEPTH {
+            return Err(RuntimeError::StackOverflow { span });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn evaluate_kind(&mut self, expr: &Expr) -> InterpreterResult<Value> {
+        match &expr.kind {
+            ExprKind::BoolLiteral(v) => Ok(Value::Bool(*v)),
+            ExprKind::StringLiteral(v) => Ok(Value::String(v.clone())),
+            ExprKind::NumberLiteral(v) => Ok(Value::Number(*v)),
+            ExprKind::Nil => Ok(Value::Nil),
+            ExprKind::Grouping(expr) => self.evaluate(expr),
+
+            ExprKind::Unary { operator, right } => {
                 let right_result = self.evaluate(right)?;
 
                 match (&operator.token_type, right_result) {
                     (TokenType::Minus, Value::Number(v)) => Ok(Value::Number(-v)),
-                    (TokenType::Bang, v) => Ok(Value::Bool(!v.is_truthy())),
-                    _ => Err(RuntimeError::InvalidOperation),
+                    (TokenType::Bang, v) => Ok(Value::Bool(!v.is_truthy(&self.config))),
+                    (TokenType::Minus, v) => Err(RuntimeError::InvalidOperand {
+                        operator: operator.lexeme.clone(),
+                        expected: "a number",
+                        got: v.type_name(),
+                        span: expr.span,
+                    }),
+                    _ => Err(RuntimeError::InvalidOperation { span: expr.span }),
                 }
             }
 
-            Expr::Binary {
+            ExprKind::Binary {
                 left,
                 operator,
                 right,
             } => {
-                let right_resut = self.evaluate(right)?;
+                // operands are evaluated in source order so their side effects are too
                 let left_result = self.evaluate(left)?;
+                let right_result = self.evaluate(right)?;
 
-                match (&operator.token_type, left_result, right_resut) {
+                match (&operator.token_type, left_result, right_result) {
                     // arithmetic
+                    // f64 division would quietly produce inf or NaN. The pattern matches -0 as well
+                    (TokenType::Slash, Value::Number(_), Value::Number(0.0))
+                        if self.config.checked_division =>
+                    {
+                        Err(RuntimeError::InvalidArithmeticOperation {
+                            reason: "division by zero",
+                            span: right.span,
+                        })
+                    }
                     (TokenType::Slash, Value::Number(l), Value::Number(r)) => {
                         Ok(Value::Number(l / r))
                     }
@@ -146,8 +326,15 @@ impl Interpreter {
                     }
 
                     // string concatenation
-                    (TokenType::Plus, Value::String(l), r) => Ok(Value::String(l + &r.to_string())),
-                    (TokenType::Plus, l, Value::String(r)) => Ok(Value::String(l.to_string() + &r)),
+                    (TokenType::Plus, Value::String(l), Value::String(r)) => {
+                        Ok(Value::String(l + &r))
+                    }
+                    (TokenType::Plus, Value::String(l), r) if self.config.mixed_concatenation => {
+                        Ok(Value::String(l + &r.to_string()))
+                    }
+                    (TokenType::Plus, l, Value::String(r)) if self.config.mixed_concatenation => {
+                        Ok(Value::String(l.to_string() + &r))
+                    }
 
                     // comparison
                     (TokenType::Greater, Value::Number(l), Value::Number(r)) => {
@@ -165,18 +352,46 @@ impl Interpreter {
                     (TokenType::EqualEqual, l, r) => Ok(Value::Bool(l == r)),
                     (TokenType::BangEqual, l, r) => Ok(Value::Bool(l != r)),
 
-                    _ => Err(RuntimeError::InvalidOperation),
+                    (TokenType::Plus, l, r) => Err(RuntimeError::InvalidOperands {
+                        operator: operator.lexeme.clone(),
+                        expected: match self.config.mixed_concatenation {
+                            true => "numbers or strings",
+                            false => "two numbers or two strings",
+                        },
+                        left: l.type_name(),
+                        right: r.type_name(),
+                        span: expr.span,
+                    }),
+                    (
+                        TokenType::Minus
+                        | TokenType::Star
+                        | TokenType::Slash
+                        | TokenType::Greater
+                        | TokenType::GreaterEqual
+                        | TokenType::Less
+                        | TokenType::LessEqual,
+                        l,
+                        r,
+                    ) => Err(RuntimeError::InvalidOperands {
+                        operator: operator.lexeme.clone(),
+                        expected: "numbers",
+                        left: l.type_name(),
+                        right: r.type_name(),
+                        span: expr.span,
+                    }),
+
+                    _ => Err(RuntimeError::InvalidOperation { span: expr.span }),
                 }
             }
 
-            Expr::Logical {
+            ExprKind::Logical {
                 left,
                 operator,
                 right,
             } => match operator.token_type {
                 TokenType::And => {
                     let left_result = self.evaluate(left)?;
-                    match left_result.is_truthy() {
+                    match left_result.is_truthy(&self.config) {
                         // short circuit
                         false => Ok(left_result),
                         // keep chaining so long as it's true
@@ -185,23 +400,971 @@ impl Interpreter {
                 }
                 TokenType::Or => {
                     let left_result = self.evaluate(left)?;
-                    match left_result.is_truthy() {
+                    match left_result.is_truthy(&self.config) {
                         // short circuit
                         true => Ok(left_result),
                         // keep chaining
                         false => self.evaluate(right),
                     }
                 }
-                _ => Err(RuntimeError::InvalidOperation),
+                _ => Err(RuntimeError::InvalidOperation { span: expr.span }),
             },
 
-            Expr::Variable { name } => Ok(self.env.get(&name.lexeme)?.clone()),
+            ExprKind::Variable { name, depth } => self.look_up(name, depth),
 
-            Expr::Assignment { name, value } => {
+            ExprKind::Function(declaration) => Ok(self.new_function(declaration)),
+
+            ExprKind::Assignment { name, value, depth } => {
                 let result = self.evaluate(value)?;
-                self.env.assign(&name.lexeme, &result)?;
+                match depth.get() {
+                    Some(depth) => self.env.assign_at(depth, &name.lexeme, &result),
+                    None => self.globals.assign(&name.lexeme, &result),
+                }
+                .map_err(|e| e.at(name.span))?;
                 Ok(result)
             }
+
+            ExprKind::Conditional {
+                condition,
+                when_true,
+                when_false,
+            } => {
+                // only the branch that is taken gets evaluated
+                if self.evaluate(condition)?.is_truthy(&self.config) {
+                    self.evaluate(when_true)
+                } else {
+                    self.evaluate(when_false)
+                }
+            }
+
+            ExprKind::Call {
+                callee, arguments, ..
+            } => {
+                let callee = self.evaluate(callee)?;
+
+                let mut values = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    values.push(self.evaluate(argument)?);
+                }
+
+                let arity = match &callee {
+                    Value::Function(function) => function.arity(),
+                    Value::Class(class) => class.arity(),
+                    _ => {
+                        return Err(RuntimeError::NotCallable {
+                            value: callee,
+                            span: expr.span,
+                        });
+                    }
+                };
+
+                if values.len() != arity {
+                    return Err(RuntimeError::ArityMismatch {
+                        expected: arity,
+                        got: values.len(),
+                        span: expr.span,
+                    });
+                }
+
+                match callee {
+                    Value::Function(function) => self.call_function(&function, values),
+                    Value::Class(class) => self.instantiate(class, values),
+                    _ => unreachable!("callee was checked above"),
+                }
+            }
+
+            ExprKind::Get { object, name } => match self.evaluate(object)? {
+                Value::Map(map) if name.lexeme == "length" => Ok(Value::Number(map.len() as f64)),
+                Value::List(list) if name.lexeme == "length" => {
+                    Ok(Value::Number(list.len() as f64))
+                }
+                Value::Instance(instance) => {
+                    instance
+                        .get(&name.lexeme)
+                        .ok_or_else(|| RuntimeError::UndefinedProperty {
+                            name: name.lexeme.clone(),
+                            span: name.span,
+                        })
+                }
+                value => Err(RuntimeError::NotAnInstance {
+                    value,
+                    span: object.span,
+                }),
+            },
+
+            ExprKind::Set {
+                object,
+                name,
+                value,
+            } => {
+                let instance = match self.evaluate(object)? {
+                    Value::Instance(instance) => instance,
+                    value => {
+                        return Err(RuntimeError::NotAnInstance {
+                            value,
+                            span: object.span,
+                        });
+                    }
+                };
+
+                let result = self.evaluate(value)?;
+                instance.set(&name.lexeme, &result);
+                Ok(result)
+            }
+
+            ExprKind::List(elements) => {
+                let items = elements
+                    .iter()
+                    .map(|element| self.evaluate(element))
+                    .collect::<InterpreterResult<_>>()?;
+                Ok(Value::List(Rc::new(List::new(items))))
+            }
+
+            // parts are joined whatever their type, unlike `+`
+            ExprKind::Interpolation(parts) => {
+                let mut result = String::new();
+                for part in parts {
+                    result.push_str(&self.evaluate(part)?.to_string());
+                }
+                Ok(Value::String(result))
+            }
+
+            ExprKind::Map(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| Ok((self.evaluate_key(key)?, self.evaluate(value)?)))
+                    .collect::<InterpreterResult<_>>()?;
+                Ok(Value::Map(Rc::new(Map::new(entries))))
+            }
+
+            ExprKind::Index { object, index } => match self.evaluate(object)? {
+                Value::List(list) => {
+                    let position = self.evaluate_index(&list, index)?;
+                    Ok(list
+                        .get(position)
+                        .expect("Index was checked against the length"))
+                }
+                // missing keys read as nil
+                Value::Map(map) => {
+                    let key = self.evaluate_key(index)?;
+                    Ok(map.get(&key).unwrap_or(Value::Nil))
+                }
+                value => Err(RuntimeError::NotIndexable {
+                    got: value.type_name(),
+                    span: object.span,
+                }),
+            },
+
+            ExprKind::SetIndex {
+                object,
+                index,
+                value,
+            } => match self.evaluate(object)? {
+                Value::List(list) => {
+                    let position = self.evaluate_index(&list, index)?;
+                    let result = self.evaluate(value)?;
+                    list.set(position, &result)
+                        .expect("Index was checked against the length");
+                    Ok(result)
+                }
+                Value::Map(map) => {
+                    let key = self.evaluate_key(index)?;
+                    let result = self.evaluate(value)?;
+                    map.set(key, &result);
+                    Ok(result)
+                }
+                value => Err(RuntimeError::NotIndexable {
+                    got: value.type_name(),
+                    span: object.span,
+                }),
+            },
+
+            ExprKind::This { keyword, depth } => self.look_up(keyword, depth),
+
+            ExprKind::Super {
+                keyword,
+                method,
+                depth,
+            } => {
+                let depth = depth
+                    .get()
+                    .expect("super is always resolved to a local scope");
+
+                let Value::Class(superclass) = self
+                    .env
+                    .get_at(depth, "super")
+                    .map_err(|e| e.at(keyword.span))?
+                else {
+                    unreachable!("super is only ever bound to a class")
+                };
+                // `this` is bound in the scope right inside the one that defines `super`
+                let Value::Instance(instance) = self
+                    .env
+                    .get_at(depth - 1, "this")
+                    .map_err(|e| e.at(keyword.span))?
+                else {
+                    unreachable!("this is only ever bound to an instance")
+                };
+
+                let method = superclass.find_method(&method.lexeme).ok_or_else(|| {
+                    RuntimeError::UndefinedProperty {
+                        name: method.lexeme.clone(),
+                        span: method.span,
+                    }
+                })?;
+
+                Ok(Value::Function(Rc::new(method.bind(instance))))
+            }
+        }
+    }
+
+    fn evaluate_key(&mut self, key: &Expr) -> InterpreterResult<MapKey> {
+        let value = self.evaluate(key)?;
+        MapKey::try_from(&value).map_err(|error| match error {
+            InvalidKey::Nan => RuntimeError::NanKey { span: key.span },
+            InvalidKey::Unhashable => RuntimeError::UnhashableKey {
+                got: value.type_name(),
+                span: key.span,
+            },
+        })
+    }
+
+    /// Evaluate `index` into a position that is in bounds for `list`
+    fn evaluate_index(&mut self, list: &List, index: &Expr) -> InterpreterResult<usize> {
+        let span = index.span;
+        match self.evaluate(index)? {
+            Value::Number(n) if n < 0.0 => Err(RuntimeError::NegativeIndex { index: n, span }),
+            Value::Number(n) if n.fract() != 0.0 => Err(RuntimeError::InvalidIndex {
+                got: n.to_string(),
+                span,
+            }),
+            // compared before casting, since huge numbers would saturate to `usize::MAX`
+            Value::Number(n) if n < list.len() as f64 => Ok(n as usize),
+            Value::Number(n) => Err(RuntimeError::IndexOutOfBounds {
+                index: n,
+                length: list.len(),
+                span,
+            }),
+            value => Err(RuntimeError::InvalidIndex {
+                got: value.type_name().to_string(),
+                span,
+            }),
+        }
+    }
+
+    fn look_up(&self, name: &Token, depth: &Depth) -> InterpreterResult<Value> {
+        match depth.get() {
+            Some(depth) => self.env.get_at(depth, &name.lexeme),
+            None => self.globals.get(&name.lexeme),
+        }
+        .map_err(|e| e.at(name.span))
+    }
+
+    fn instantiate(&mut self, class: Rc<Class>, arguments: Vec<Value>) -> InterpreterResult<Value> {
+        let instance = Rc::new(Instance::new(Rc::clone(&class)));
+
+        if let Some(initializer) = class.find_method("init") {
+            self.call_function(&initializer.bind(Rc::clone(&instance)), arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+
+    /// Create a function value that captures the current scope chain
+    fn new_function(&self, declaration: &Rc<FunctionDecl>) -> Value {
+        let function = Function::new(Rc::clone(declaration), self.env.clone());
+        Value::Function(Rc::new(function))
+    }
+
+    fn call_function(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Value>,
+    ) -> InterpreterResult<Value> {
+        let declaration = function.declaration();
+        let mut env = Env::new_enclosed(function.closure());
+
+        for (param, argument) in declaration.params.iter().zip(arguments) {
+            env.define(&param.lexeme, &argument);
+        }
+
+        let value = match self.with_env(env, |this| this.execute_all(&declaration.body))? {
+            Completion::Return(value) => value,
+            _ => Value::Nil,
+        };
+
+        match function.is_initializer() {
+            true => Ok(function
+                .closure()
+                .get_at(0, "this")
+                .expect("Initializers always have this bound")),
+            false => Ok(value),
+        }
+    }
+}
+
+/// Whether a loop carries on after its body completed, or stops with the completion of the whole
+/// loop statement. Loop control aimed at an enclosing loop keeps unwinding with one less loop to
+/// go.
+fn after_body(completion: Completion) -> ControlFlow<Completion> {
+    match completion {
+        Completion::Normal | Completion::Continue(0) => ControlFlow::Continue(()),
+        Completion::Break(0) => ControlFlow::Break(Completion::Normal),
+        Completion::Continue(depth) => ControlFlow::Break(Completion::Continue(depth - 1)),
+        Completion::Break(depth) => ControlFlow::Break(Completion::Break(depth - 1)),
+        Completion::Return(value) => ControlFlow::Break(Completion::Return(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Lox, LoxError,
+        frontend::{FileId, Parser, Resolver, Scanner},
+    };
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> InterpreterResult<()> {
+        let mut scanner = Scanner::new(source, FileId::default(), interpreter.config);
+        let (tokens, _) = scanner.scan_tokens();
+        let stmts = Parser::new(tokens).parse().expect("valid syntax");
+        Resolver::new(interpreter.config)
+            .resolve(&stmts)
+            .expect("valid syntax");
+        interpreter.interpret(&stmts)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Value {
+        interpreter.globals.get(name).expect("defined global")
+    }
+
+    fn runtime_error(source: &str) -> String {
+        match Lox::default().run("test.lox", source) {
+            Err(LoxError::Runtime(error)) => error.to_string(),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn operand_type_errors() {
+        assert_eq!(
+            runtime_error("\"a\" - nil;"),
+            "Operands of `-` must be numbers, got string and nil"
+        );
+        assert_eq!(
+            runtime_error("-true;"),
+            "Operand of unary `-` must be a number, got bool"
+        );
+        assert_eq!(
+            runtime_error("nil + 1;"),
+            "Operands of `+` must be numbers or strings, got nil and number"
+        );
+        assert_eq!(
+            runtime_error("1 < \"2\";"),
+            "Operands of `<` must be numbers, got number and string"
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(
+            runtime_error("1 / 0;"),
+            "Invalid arithmetic operation: division by zero"
+        );
+        assert!(Lox::default().run("test.lox", "0 / 1;").is_ok());
+    }
+
+    #[test]
+    fn deep_recursion() {
+        // values can't leave the thread, so it sends back the error messages
+        let (error, after) = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| {
+                let mut lox = Lox::default();
+                let error = lox.run(
+                    "test.lox",
+                    "fun f(n) { if (n == 0) return 0; return 1 + f(n - 1); }
+                    var shallow = f(500);
+                    f(5000);",
+                );
+
+                // the depth is reset after the error, so calls still work
+                let after = lox.run("test.lox", "f(10);");
+                (error.map_err(|e| e.to_string()), after.is_ok())
+            })
+            .unwrap()
+            .join()
+            .expect("a Lox error, not a native stack overflow");
+
+        assert_eq!(
+            error,
+            Err(
+                "Runtime error: Stack overflow: calls and expressions are nested too deeply"
+                    .to_string()
+            )
+        );
+        assert!(after);
+    }
+
+    #[test]
+    fn deeply_nested_expressions() {
+        let results = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| {
+                // every call nests the next one inside ten more expressions
+                let source = "fun f(n) {
+                    if (n == 0) return 0;
+                    return 1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + f(n - 1))))))))));
+                }";
+                let mut lox = Lox::default();
+                lox.run("test.lox", source).unwrap();
+                [
+                    lox.run("test.lox", "f(100);"),
+                    lox.run("test.lox", "f(999);"),
+                ]
+                .map(|result| result.map_err(|e| e.to_string()))
+            })
+            .unwrap()
+            .join()
+            .expect("a Lox error, not a native stack overflow");
+
+        assert_eq!(
+            results,
+            [
+                Ok(()),
+                Err(
+                    "Runtime error: Stack overflow: calls and expressions are nested too deeply"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "fun add(a, b) { return a + b; }
+            var sum = add(1, 2);
+
+            fun fib(n) {
+                if (n < 2) return n;
+                return fib(n - 1) + fib(n - 2);
+            }
+            var fib10 = fib(10);
+
+            // a bare return and falling off the end both return nil
+            fun early() { return; }
+            fun none() {}
+            var early = early();
+            var none = none();",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "sum"), Value::Number(3.0));
+        assert_eq!(global(&interpreter, "fib10"), Value::Number(55.0));
+        assert_eq!(global(&interpreter, "early"), Value::Nil);
+        assert_eq!(global(&interpreter, "none"), Value::Nil);
+    }
+
+    #[test]
+    fn call_errors() {
+        assert_eq!(
+            runtime_error("fun f(a) {} f(1, 2);"),
+            "Expected 1 arguments but got 2"
+        );
+        assert_eq!(
+            runtime_error("var f = 1; f();"),
+            "Can only call functions and classes, got 1"
+        );
+    }
+
+    #[test]
+    fn closures_outlive_their_scope() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "fun makeCounter() {
+                var count = 0;
+                fun increment() { count = count + 1; return count; }
+                return increment;
+            }
+            var counter = makeCounter();
+            counter();
+            var second = counter();
+            // every call to makeCounter creates a separate count
+            var other = makeCounter()();
+
+            var callback;
+            {
+                var greeting = \"hi \";
+                callback = fun (name) { return greeting + name; };
+            }
+            var greeted = callback(\"bob\");",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "second"), Value::Number(2.0));
+        assert_eq!(global(&interpreter, "other"), Value::Number(1.0));
+        assert_eq!(
+            global(&interpreter, "greeted"),
+            Value::String("hi bob".to_string())
+        );
+    }
+
+    #[test]
+    fn classes() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "class Point {
+                init(x, y) { this.x = x; this.y = y; }
+                sum() { return this.x + this.y; }
+            }
+            var p = Point(1, 2);
+            p.y = 10;
+            var sum = p.sum();
+
+            // methods stay bound to their instance when passed around
+            var method = p.sum;
+            p.x = 5;
+            var bound = method();
+
+            // fields shadow methods
+            p.sum = 0;
+            var field = p.sum;
+
+            // calling init again still returns the instance, even after an early return
+            var same = p.init(3, 4) == p;
+            class Early { init() { return; } }
+            var early = Early().init();",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "sum"), Value::Number(11.0));
+        assert_eq!(global(&interpreter, "bound"), Value::Number(15.0));
+        assert_eq!(global(&interpreter, "field"), Value::Number(0.0));
+        assert_eq!(global(&interpreter, "same"), Value::Bool(true));
+        assert_eq!(global(&interpreter, "early").to_string(), "Early instance");
+    }
+
+    #[test]
+    fn inheritance() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "class A {
+                name() { return \"A\"; }
+                describe() { return \"I am \" + this.name(); }
+            }
+            class B < A {
+                name() { return \"B\"; }
+                parent() { return super.name(); }
+            }
+            class C < B {
+                // found on A through B, and called with C's instance as this
+                shout() { return super.describe() + \"!\"; }
+            }
+
+            var inherited = B().describe();
+            var parent = B().parent();
+            var chained = C().shout();
+            var grandparent = C().parent();",
+        )
+        .unwrap();
+
+        assert_eq!(
+            global(&interpreter, "inherited"),
+            Value::String("I am B".to_string())
+        );
+        assert_eq!(
+            global(&interpreter, "parent"),
+            Value::String("A".to_string())
+        );
+        assert_eq!(
+            global(&interpreter, "chained"),
+            Value::String("I am B!".to_string())
+        );
+        assert_eq!(
+            global(&interpreter, "grandparent"),
+            Value::String("A".to_string())
+        );
+    }
+
+    #[test]
+    fn class_errors() {
+        assert_eq!(
+            runtime_error("var A = 1; class B < A {}"),
+            "Superclass must be a class, got 1"
+        );
+        assert_eq!(
+            runtime_error("class A {} A().missing;"),
+            "Undefined property: missing"
+        );
+        assert_eq!(
+            runtime_error("class A {} class B < A { f() { return super.missing; } } B().f();"),
+            "Undefined property: missing"
+        );
+        assert_eq!(
+            runtime_error("var n = 1; n.field;"),
+            "Only instances have properties, got 1"
+        );
+        assert_eq!(
+            runtime_error("\"s\".field = 1;"),
+            "Only instances have properties, got s"
+        );
+        assert_eq!(
+            runtime_error("class A { init(a, b) {} } A(1);"),
+            "Expected 2 arguments but got 1"
+        );
+        // without init, a class takes no arguments
+        assert_eq!(
+            runtime_error("class A {} A(1);"),
+            "Expected 0 arguments but got 1"
+        );
+    }
+
+    #[test]
+    fn conditional_evaluates_one_branch() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var taken = \"\";
+            fun log(name) { taken = taken + name; return name; }
+            var first = true ? log(\"a\") : log(\"b\");
+            var second = nil ? log(\"c\") : false ? log(\"d\") : log(\"e\");",
+        )
+        .unwrap();
+
+        assert_eq!(
+            global(&interpreter, "taken"),
+            Value::String("ae".to_string())
+        );
+        assert_eq!(
+            global(&interpreter, "second"),
+            Value::String("e".to_string())
+        );
+    }
+
+    #[test]
+    fn interpolation_joins_any_values() {
+        // interpolation doesn't depend on what `+` accepts
+        let config = LoxConfig {
+            mixed_concatenation: false,
+            ..LoxConfig::default()
+        };
+        let mut interpreter = Interpreter::new(config);
+        run(
+            &mut interpreter,
+            "var n = 3;
+            var s = \"n=${n}, ${nil} ${[n]}${\"!\"}\";",
+        )
+        .unwrap();
+
+        assert_eq!(
+            global(&interpreter, "s"),
+            Value::String("n=3, nil [3]!".to_string())
+        );
+    }
+
+    #[test]
+    fn loop_control_in_nested_blocks() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var count = 0;
+            for (var i = 0; i < 5; i = i + 1) {
+                { { if (i == 1) continue; } }
+                { if (i == 3) { break; } }
+                count = count + 1;
+            }
+            var after = count;",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "count"), Value::Number(2.0));
+        // defined at the top level again, not in a scope left behind by the loop
+        assert_eq!(global(&interpreter, "after"), Value::Number(2.0));
+    }
+
+    #[test]
+    fn break_skips_the_increment() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var last = nil;
+            for (var i = 0; i < 5; i = i + 1) { last = i; { break; } }
+            var j = 0;
+            while (true) { { j = j + 1; if (j == 2) break; } }",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "last"), Value::Number(0.0));
+        assert_eq!(global(&interpreter, "j"), Value::Number(2.0));
+    }
+
+    #[test]
+    fn errors_exit_every_scope() {
+        let mut interpreter = Interpreter::default();
+        let error = run(
+            &mut interpreter,
+            "for (var i = 0; i < 1; i = i + 1) { { var a = 1; a - nil; } }",
+        );
+        assert!(error.is_err());
+
+        // the next line of the REPL runs in the global scope again
+        run(
+            &mut interpreter,
+            "var b = 1; fun f() { { return b; } } var c = f();",
+        )
+        .unwrap();
+        assert_eq!(global(&interpreter, "c"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn evaluation_order() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var order = \"\";
+            fun log(name, value) { order = order + name; return value; }
+
+            var binary = log(\"a\", 1) - log(\"b\", 2) * log(\"c\", 3);
+            var comparison = log(\"d\", 1) < log(\"e\", 2);
+
+            // the right-hand side of `and` and `or` only runs when it is needed
+            var logical = log(\"f\", false) and log(\"x\", true);
+            logical = log(\"g\", true) or log(\"x\", true);
+            logical = log(\"h\", true) and log(\"i\", true);
+
+            // the value of an assignment is evaluated before it is stored
+            var n = 1;
+            var assignment = n + (n = 10);
+            var chained;
+            chained = n = log(\"j\", 3);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            global(&interpreter, "order"),
+            Value::String("abcdefghij".to_string())
+        );
+        assert_eq!(global(&interpreter, "binary"), Value::Number(-5.0));
+        assert_eq!(global(&interpreter, "assignment"), Value::Number(11.0));
+        assert_eq!(global(&interpreter, "chained"), Value::Number(3.0));
+        assert_eq!(global(&interpreter, "n"), Value::Number(3.0));
+    }
+
+    #[test]
+    fn book_dialect() {
+        let mut interpreter = Interpreter::new(LoxConfig::book());
+        run(
+            &mut interpreter,
+            "var zero = 0 ? \"truthy\" : \"falsey\";
+            var empty = \"\" ? \"truthy\" : \"falsey\";
+            var unset;
+            var copy = unset;
+            // keywords of the extended dialect are ordinary names
+            var loop = 1;
+            var do = loop + 1;",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "zero"), Value::String("truthy".into()));
+        assert_eq!(
+            global(&interpreter, "empty"),
+            Value::String("truthy".into())
+        );
+        assert_eq!(global(&interpreter, "copy"), Value::Nil);
+        assert_eq!(global(&interpreter, "do"), Value::Number(2.0));
+
+        let mut book = Lox::new(LoxConfig::book());
+        let mut echo = |source| {
+            book.run_repl("test.lox", source)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(echo("1 / 0"), "Infinity");
+        assert_eq!(echo("-1 / 0"), "-Infinity");
+        assert_eq!(echo("0 / 0"), "NaN");
+
+        let mut book = Lox::new(LoxConfig::book());
+        assert!(matches!(
+            book.run("test.lox", "\"a\" + 1;"),
+            Err(LoxError::Runtime(RuntimeError::InvalidOperands { .. }))
+        ));
+        assert!(matches!(
+            book.run("test.lox", "while (true) break;"),
+            Err(LoxError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn extended_dialect() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var zero = 0 ? \"truthy\" : \"falsey\";
+            var mixed = \"a\" + 1;",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "zero"), Value::String("falsey".into()));
+        assert_eq!(global(&interpreter, "mixed"), Value::String("a1".into()));
+    }
+
+    #[test]
+    fn strict_uninitialized() {
+        let source = "var unset; var copy = unset;";
+        assert!(Lox::default().run("test.lox", source).is_ok());
+
+        let config = LoxConfig {
+            strict_uninitialized: true,
+            ..LoxConfig::default()
+        };
+        match Lox::new(config).run("test.lox", source) {
+            Err(LoxError::Runtime(error)) => assert_eq!(
+                error.to_string(),
+                "Variable unset is read before it is assigned a value"
+            ),
+            other => panic!("expected a runtime error, got {other:?}"),
         }
     }
+
+    #[test]
+    fn labeled_loops() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var pairs = \"\";
+            outer: for (var i = 0; i < 3; i = i + 1) {
+                for (var j = 0; j < 3; j = j + 1) {
+                    if (j == 1) continue outer;
+                    if (i == 2) break outer;
+                    pairs = pairs + i + j + \" \";
+                }
+            }
+
+            var n = 0;
+            do n = n + 1; while (n < 0);
+
+            var m = 0;
+            loop {
+                m = m + 1;
+                if (m < 3) continue;
+                break;
+            }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            global(&interpreter, "pairs"),
+            Value::String("00 10 ".to_string())
+        );
+        assert_eq!(global(&interpreter, "n"), Value::Number(1.0));
+        assert_eq!(global(&interpreter, "m"), Value::Number(3.0));
+    }
+
+    #[test]
+    fn lists() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var xs = [1, \"two\", [3],];
+            var ys = xs;
+            ys[0] = 10;
+            var first = xs[0];
+            var nested = xs[2][0];
+            var length = xs.length;
+            var text = \"\" + xs;",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "first"), Value::Number(10.0));
+        assert_eq!(global(&interpreter, "nested"), Value::Number(3.0));
+        assert_eq!(global(&interpreter, "length"), Value::Number(3.0));
+        assert_eq!(
+            global(&interpreter, "text"),
+            Value::String("[10, \"two\", [3]]".to_string())
+        );
+    }
+
+    #[test]
+    fn list_index_errors() {
+        assert_eq!(
+            runtime_error("[1, 2][2];"),
+            "Index 2 is out of bounds for a list of length 2"
+        );
+        assert_eq!(
+            runtime_error("[1][1e30];"),
+            "Index 1000000000000000000000000000000 is out of bounds for a list of length 1"
+        );
+        assert_eq!(
+            runtime_error("[1][-1] = 2;"),
+            "List index can't be negative, got -1"
+        );
+        assert_eq!(
+            runtime_error("[1][0.5];"),
+            "List index must be a whole number, got 0.5"
+        );
+        assert_eq!(
+            runtime_error("nil[0];"),
+            "Only lists and maps can be indexed, got nil"
+        );
+    }
+
+    #[test]
+    fn maps() {
+        let mut interpreter = Interpreter::default();
+        run(
+            &mut interpreter,
+            "var m = {\"a\": 1, 2: \"two\", true: nil};
+            var alias = m;
+            alias[-0] = \"zero\";
+            var zero = m[0];
+            var missing = m[\"b\"];
+            var length = m.length;
+            var text = \"\" + m;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            global(&interpreter, "zero"),
+            Value::String("zero".to_string())
+        );
+        assert_eq!(global(&interpreter, "missing"), Value::Nil);
+        assert_eq!(global(&interpreter, "length"), Value::Number(4.0));
+        assert_eq!(
+            global(&interpreter, "text"),
+            Value::String("{\"a\": 1, 2: \"two\", true: nil, 0: \"zero\"}".to_string())
+        );
+        assert_eq!(
+            runtime_error("var m = {}; m[[]] = 1;"),
+            "Map keys must be strings, numbers, booleans or nil, got list"
+        );
+    }
+
+    #[test]
+    fn repl_values() {
+        let mut lox = Lox::default();
+        let mut echo = |source| {
+            lox.run_repl("test.lox", source)
+                .unwrap()
+                .map(|value| value.repr())
+        };
+
+        assert_eq!(echo("var s = \"a\";"), None);
+        assert_eq!(echo("s + \"b\""), Some("\"ab\"".to_string()));
+        assert_eq!(echo("1; nil;"), Some("nil".to_string()));
+        assert_eq!(echo("s; var t = 1;"), None);
+
+        // strings are escaped so that they read back as the same value
+        assert_eq!(
+            echo(r#""say \"hi\"\nbye\t\\ \$""#),
+            Some(r#""say \"hi\"\nbye\t\\ \$""#.to_string())
+        );
+        assert_eq!(echo(r#"["a\", \"b"]"#), Some(r#"["a\", \"b"]"#.to_string()));
+    }
 }
@@ -0,0 +1,79 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+};
+
+use super::Value;
+
+/// A sequence of values. Its length is fixed by the literal that creates it, but its elements can
+/// be reassigned.
+///
+/// Lists are shared by reference: every variable holding the same list sees changes made through
+/// any of them.
+#[derive(Debug, Default)]
+pub struct List {
+    items: RefCell<Vec<Value>>,
+
+    /// Set while the list is being displayed so that a list containing itself doesn't recurse
+    /// forever
+    displaying: Cell<bool>,
+}
+
+impl List {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self {
+            items: RefCell::new(items),
+            displaying: Cell::new(false),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.borrow().is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Value> {
+        self.items.borrow().get(index).cloned()
+    }
+
+    /// Replace the item at `index`. Returns `None` if the index is out of bounds.
+    pub fn set(&self, index: usize, value: &Value) -> Option<()> {
+        let mut items = self.items.borrow_mut();
+        let slot = items.get_mut(index)?;
+        *slot = value.clone();
+        Some(())
+    }
+}
+
+// Lists are only ever equal to themselves, like instances
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.displaying.replace(true) {
+            return write!(f, "[...]");
+        }
+
+        let result = (|| {
+            write!(f, "[")?;
+            for (i, item) in self.items.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                // quoted so that `["a, b"]` can be told apart from `["a", "b"]`
+                write!(f, "{}", item.repr())?;
+            }
+            write!(f, "]")
+        })();
+
+        self.displaying.set(false);
+        result
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+};
+
+use super::Value;
+
+/// The hashable subset of values that can be used as map keys
+///
+/// Numbers are stored by their bits, with two rules so that keys match the way `==` compares
+/// numbers:
+///
+/// - `-0` is stored as `0`, because `-0 == 0`
+/// - NaN is never a key, because `NaN == NaN` is false and the entry could never be found again
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Nil,
+}
+
+#[derive(Debug)]
+pub enum InvalidKey {
+    Nan,
+    Unhashable,
+}
+
+impl TryFrom<&Value> for MapKey {
+    type Error = InvalidKey;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bool(v) => Ok(Self::Bool(*v)),
+            Value::Number(v) if v.is_nan() => Err(InvalidKey::Nan),
+            // `-0.0 + 0.0` is `0.0`, every other number is unchanged
+            Value::Number(v) => Ok(Self::Number((v + 0.0).to_bits())),
+            Value::String(v) => Ok(Self::String(v.clone())),
+            Value::Nil => Ok(Self::Nil),
+            _ => Err(InvalidKey::Unhashable),
+        }
+    }
+}
+
+impl From<&MapKey> for Value {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Bool(v) => Value::Bool(*v),
+            MapKey::Number(v) => Value::Number(f64::from_bits(*v)),
+            MapKey::String(v) => Value::String(v.clone()),
+            MapKey::Nil => Value::Nil,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Position of each key in `entries`
+    positions: HashMap<MapKey, usize>,
+
+    /// Kept in insertion order so that maps always display the same way
+    entries: Vec<(MapKey, Value)>,
+}
+
+/// A mapping from keys to values
+///
+/// Maps are shared by reference: every variable holding the same map sees changes made through
+/// any of them.
+#[derive(Debug, Default)]
+pub struct Map {
+    entries: RefCell<Entries>,
+
+    /// Set while the map is being displayed so that a map containing itself doesn't recurse
+    /// forever
+    displaying: Cell<bool>,
+}
+
+impl Map {
+    pub fn new(entries: Vec<(MapKey, Value)>) -> Self {
+        let map = Self::default();
+        for (key, value) in entries {
+            map.set(key, &value);
+        }
+
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().entries.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<Value> {
+        let entries = self.entries.borrow();
+        let position = *entries.positions.get(key)?;
+        Some(entries.entries[position].1.clone())
+    }
+
+    pub fn set(&self, key: MapKey, value: &Value) {
+        let mut entries = self.entries.borrow_mut();
+        match entries.positions.get(&key) {
+            Some(&position) => entries.entries[position].1 = value.clone(),
+            None => {
+                let position = entries.entries.len();
+                entries.positions.insert(key.clone(), position);
+                entries.entries.push((key, value.clone()));
+            }
+        }
+    }
+}
+
+// Maps are only ever equal to themselves, like instances
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.displaying.replace(true) {
+            return write!(f, "{{...}}");
+        }
+
+        let result = (|| {
+            write!(f, "{{")?;
+            for (i, (key, value)) in self.entries.borrow().entries.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: {}", Value::from(key).repr(), value.repr())?;
+            }
+            write!(f, "}}")
+        })();
+
+        self.displaying.set(false);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: Value) -> Result<MapKey, InvalidKey> {
+        MapKey::try_from(&value)
+    }
+
+    #[test]
+    fn negative_zero_is_zero() {
+        assert_eq!(
+            key(Value::Number(-0.0)).unwrap(),
+            key(Value::Number(0.0)).unwrap()
+        );
+    }
+
+    #[test]
+    fn nan_is_not_a_key() {
+        assert!(matches!(key(Value::Number(f64::NAN)), Err(InvalidKey::Nan)));
+    }
+
+    #[test]
+    fn keeps_insertion_order() {
+        let map = Map::default();
+        map.set(MapKey::String("b".to_string()), &Value::Number(1.0));
+        map.set(MapKey::Nil, &Value::Bool(true));
+        map.set(MapKey::String("b".to_string()), &Value::Number(2.0));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.to_string(), "{\"b\": 2, nil: true}");
+    }
+}
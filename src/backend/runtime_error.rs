@@ -1,29 +1,123 @@
-use crate::ast::Expr;
+use super::Value;
+use crate::{ast::Expr, frontend::Span};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum RuntimeError {
     #[error("Invalid operation")]
-    InvalidOperation,
+    InvalidOperation { span: Span },
 
-    #[error("Invalid arithmetic operation")]
-    InvalidArithmeticOperation,
+    #[error("Operand of unary `{operator}` must be {expected}, got {got}")]
+    InvalidOperand {
+        operator: String,
+        expected: &'static str,
+        got: &'static str,
+        span: Span,
+    },
+
+    #[error("Operands of `{operator}` must be {expected}, got {left} and {right}")]
+    InvalidOperands {
+        operator: String,
+        expected: &'static str,
+        left: &'static str,
+        right: &'static str,
+        span: Span,
+    },
+
+    #[error("Invalid arithmetic operation: {reason}")]
+    InvalidArithmeticOperation { reason: &'static str, span: Span },
 
     #[error("Unimplemented expression: {expr}")]
-    Unimplemented { expr: Expr },
+    Unimplemented { expr: Box<Expr> },
+
+    #[error("Can only call functions and classes, got {value}")]
+    NotCallable { value: Value, span: Span },
+
+    #[error("Expected {expected} arguments but got {got}")]
+    ArityMismatch {
+        expected: usize,
+        got: usize,
+        span: Span,
+    },
+
+    #[error("Stack overflow: calls and expressions are nested too deeply")]
+    StackOverflow { span: Span },
+
+    #[error("Only instances have properties, got {value}")]
+    NotAnInstance { value: Value, span: Span },
+
+    #[error("Only lists and maps can be indexed, got {got}")]
+    NotIndexable { got: &'static str, span: Span },
+
+    #[error("Map keys must be strings, numbers, booleans or nil, got {got}")]
+    UnhashableKey { got: &'static str, span: Span },
+
+    #[error("NaN can't be used as a map key")]
+    NanKey { span: Span },
 
-    #[error("Environment error: {0}")]
-    Environment(#[from] EnvironmentError),
+    #[error("List index must be a whole number, got {got}")]
+    InvalidIndex { got: String, span: Span },
 
-    #[error("Continue")]
-    Continue,
+    #[error("List index can't be negative, got {index}")]
+    NegativeIndex { index: f64, span: Span },
 
-    #[error("Break")]
-    Break,
+    #[error("Index {index} is out of bounds for a list of length {length}")]
+    IndexOutOfBounds {
+        index: f64,
+        length: usize,
+        span: Span,
+    },
+
+    #[error("Undefined property: {name}")]
+    UndefinedProperty { name: String, span: Span },
+
+    #[error("Superclass must be a class, got {value}")]
+    InvalidSuperclass { value: Value, span: Span },
+
+    #[error("{source}")]
+    Environment {
+        source: EnvironmentError,
+        span: Span,
+    },
 }
 
 #[derive(Error, Debug)]
 pub enum EnvironmentError {
     #[error("Undefined variable: {name}")]
     UndefinedVariable { name: String },
+
+    #[error("Variable {name} is read before it is assigned a value")]
+    UninitializedVariable { name: String },
+}
+
+impl RuntimeError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::InvalidOperation { span }
+            | Self::InvalidOperand { span, .. }
+            | Self::InvalidOperands { span, .. }
+            | Self::InvalidArithmeticOperation { span, .. }
+            | Self::NotCallable { span, .. }
+            | Self::ArityMismatch { span, .. }
+            | Self::StackOverflow { span }
+            | Self::NotAnInstance { span, .. }
+            | Self::NotIndexable { span, .. }
+            | Self::UnhashableKey { span, .. }
+            | Self::NanKey { span }
+            | Self::InvalidIndex { span, .. }
+            | Self::NegativeIndex { span, .. }
+            | Self::IndexOutOfBounds { span, .. }
+            | Self::UndefinedProperty { span, .. }
+            | Self::InvalidSuperclass { span, .. }
+            | Self::Environment { span, .. } => *span,
+            Self::Unimplemented { expr } => expr.span,
+        }
+    }
+}
+
+impl EnvironmentError {
+    /// Attach the span of the variable access that failed
+    pub fn at(self, span: Span) -> RuntimeError {
+        RuntimeError::Environment { source: self, span }
+    }
 }
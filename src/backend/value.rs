@@ -1,20 +1,76 @@
-use std::fmt;
+use std::{fmt, rc::Rc};
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+use super::{Class, Function, Instance, List, Map};
+use crate::LoxConfig;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Bool(bool),
     Number(f64),
     String(String),
+    Function(Rc<Function>),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    List(Rc<List>),
+    Map(Rc<Map>),
     Nil,
 }
 
 impl Value {
-    pub fn is_truthy(&self) -> bool {
+    /// The name of the value's type, as used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Function(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Nil => "nil",
+        }
+    }
+
+    /// The value as it would be written in source, which quotes strings and escapes their
+    /// contents. Used to display values inside collections.
+    pub fn repr(&self) -> String {
+        match self {
+            Value::String(v) => {
+                let mut repr = String::from('"');
+                for c in v.chars() {
+                    match c {
+                        '\\' => repr.push_str("\\\\"),
+                        '"' => repr.push_str("\\\""),
+                        '$' => repr.push_str("\\$"),
+                        '\n' => repr.push_str("\\n"),
+                        '\t' => repr.push_str("\\t"),
+                        '\r' => repr.push_str("\\r"),
+                        '\0' => repr.push_str("\\0"),
+                        c => repr.push(c),
+                    }
+                }
+                repr.push('"');
+                repr
+            }
+            v => v.to_string(),
+        }
+    }
+
+    /// Only `nil` and `false` are falsey in the book. The extended dialect also treats `0`,
+    /// negative numbers and `""` as falsey.
+    pub fn is_truthy(&self, config: &LoxConfig) -> bool {
         match &self {
             Value::Nil => false,
             Value::Bool(v) => *v,
-            Value::Number(v) => *v > 0.0,
-            Value::String(v) => !v.is_empty(),
+            Value::Number(v) if config.extended_truthiness => *v > 0.0,
+            Value::String(v) if config.extended_truthiness => !v.is_empty(),
+            Value::Number(_) | Value::String(_) => true,
+            Value::Function(_) => true,
+            Value::Class(_) => true,
+            Value::Instance(_) => true,
+            Value::List(_) => true,
+            Value::Map(_) => true,
         }
     }
 }
@@ -23,8 +79,16 @@ impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::String(v) => write!(f, "{v}"),
+            // spelled the way jlox prints them
+            Self::Number(v) if v.is_infinite() && *v > 0.0 => write!(f, "Infinity"),
+            Self::Number(v) if v.is_infinite() => write!(f, "-Infinity"),
             Self::Number(v) => write!(f, "{v}"),
             Self::Bool(v) => write!(f, "{v}"),
+            Self::Function(v) => write!(f, "{v}"),
+            Self::Class(v) => write!(f, "{v}"),
+            Self::Instance(v) => write!(f, "{v}"),
+            Self::List(v) => write!(f, "{v}"),
+            Self::Map(v) => write!(f, "{v}"),
             Self::Nil => write!(f, "nil"),
         }
     }
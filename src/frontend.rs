@@ -1,10 +1,14 @@
 mod parser;
+mod resolver;
 mod scanner;
+mod source_map;
 mod syntax_error;
 mod token;
 
 // re-export as a flat package
 pub use parser::*;
+pub use resolver::*;
 pub use scanner::*;
+pub use source_map::*;
 pub use syntax_error::*;
 pub use token::*;
@@ -1,40 +1,88 @@
-use std::{iter::Peekable, slice::Iter};
+use std::{iter::Peekable, rc::Rc, slice::Iter};
 
 use super::token::{Token, TokenType};
-use crate::ast::{Expr, Stmt};
-use crate::frontend::ParserError;
+use crate::ast::{Expr, ExprKind, FunctionDecl, Stmt, StmtKind};
+use crate::frontend::{ParserError, Span};
 
 pub type ParserResult<T> = Result<T, ParserError>;
 
+/// Same limit as the book so that scripts remain portable to clox
+const MAX_ARGUMENTS: usize = 255;
+
 pub struct Parser<'a> {
     iter: Peekable<Iter<'a, Token>>,
+
+    /// Span of the last consumed token. Used to find where nodes end.
+    previous: Span,
+
+    /// Errors found so far. Parsing carries on after synchronising so that they can all be
+    /// reported at once.
+    errors: Vec<ParserError>,
+
+    /// Whether the last top-level statement can be an expression without a `;`, like in the REPL
+    trailing_expression: bool,
+
+    /// Byte offset where the top-level statement being parsed starts
+    statement_start: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a [Token]) -> Self {
         Self {
             iter: tokens.iter().peekable(),
+            // there is always at least an `Eof` token, which is in the same source as the rest
+            previous: tokens
+                .first()
+                .map(|token| Span::at(token.span.file, 0))
+                .unwrap_or_default(),
+            errors: Vec::new(),
+            trailing_expression: false,
+            statement_start: 0,
         }
     }
 
-    pub fn parse(&mut self) -> ParserResult<Vec<Stmt>> {
+    /// Let the input end with an expression that has no `;` -e.g. `1 + 2` in the REPL
+    pub fn allow_trailing_expression(mut self) -> Self {
+        self.trailing_expression = true;
+        self
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParserError>> {
         let mut stmts = Vec::new();
 
-        while matches!(self.iter.peek(), Some(token) if token.token_type != TokenType::Eof) {
-            match self.declaration() {
-                Ok(stmt) => stmts.push(stmt),
-                Err(e) => {
-                    self.sychronise();
-                    return Err(e);
-                }
+        while let Some(token) = self.iter.peek()
+            && token.token_type != TokenType::Eof
+        {
+            self.statement_start = token.span.start;
+            if let Some(stmt) = self.declaration_or_synchronise() {
+                stmts.push(stmt);
             }
         }
 
-        Ok(stmts)
+        match self.errors.is_empty() {
+            true => Ok(stmts),
+            false => Err(std::mem::take(&mut self.errors)),
+        }
+    }
+
+    /// Record the error, if any, and skip to the next statement boundary
+    fn declaration_or_synchronise(&mut self) -> Option<Stmt> {
+        match self.declaration() {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.errors.push(e);
+                self.sychronise();
+                None
+            }
+        }
     }
 
     fn sychronise(&mut self) {
-        while let Some(token) = self.advance() {
+        // Eof is never consumed so that callers can still tell the input is exhausted
+        while let Some(token) = self
+            .iter
+            .next_if(|token| token.token_type != TokenType::Eof)
+        {
             // statement boundary reached reached
             if token.token_type == TokenType::Semicolon {
                 return;
@@ -48,6 +96,8 @@ impl<'a> Parser<'a> {
                     | TokenType::For
                     | TokenType::If
                     | TokenType::While
+                    | TokenType::Do
+                    | TokenType::Loop
                     | TokenType::Break
                     | TokenType::Continue
                     | TokenType::Print
@@ -60,15 +110,44 @@ impl<'a> Parser<'a> {
     }
 
     fn advance(&mut self) -> Option<&Token> {
-        self.iter.next()
+        let token = self.iter.next()?;
+        self.previous = token.span;
+        Some(token)
+    }
+
+    /// Span from `start` to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous)
     }
 
     fn check(&mut self, token_type: &TokenType) -> bool {
         matches!(self.iter.peek(), Some(t) if t.token_type == *token_type)
     }
 
+    /// Look one token past the next one
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        let mut iter = self.iter.clone();
+        iter.next();
+        matches!(iter.peek(), Some(t) if t.token_type == *token_type)
+    }
+
     fn is_eof(&mut self) -> bool {
-        self.check(&TokenType::Eof)
+        matches!(
+            self.iter.peek(),
+            None | Some(Token {
+                token_type: TokenType::Eof,
+                ..
+            })
+        )
+    }
+
+    /// Clone the next token for error reporting. The scanner always emits Eof so the iterator is
+    /// only exhausted once Eof has been consumed.
+    fn peek_token(&mut self) -> Token {
+        self.iter
+            .peek()
+            .map(|token| (*token).clone())
+            .expect("Token stream must end with Eof")
     }
 
     fn match_tokens(&mut self, token_types: &[TokenType]) -> Option<Token> {
@@ -114,13 +193,17 @@ impl<'a> Parser<'a> {
 
     // TODO: add production rules
     fn assignment(&mut self) -> ParserResult<Expr> {
-        let expr = self.or()?;
+        let expr = self.conditional()?;
 
         if let Some(equals) = self.match_tokens(&[TokenType::Equal]) {
             let value = self.assignment()?;
 
-            return match expr {
-                Expr::Variable { name } => Ok(Expr::new_assignment(name, value)),
+            return match expr.kind {
+                ExprKind::Variable { name, .. } => Ok(Expr::new_assignment(name, value)),
+                ExprKind::Get { object, name } => Ok(Expr::new_set(*object, name, value)),
+                ExprKind::Index { object, index } => {
+                    Ok(Expr::new_set_index(*object, *index, value))
+                }
                 _ => Err(ParserError::InvalidAssignmentTarget { token: equals }),
             };
         }
@@ -128,6 +211,22 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// conditional → or ( "?" expression ":" conditional )? ;
+    fn conditional(&mut self) -> ParserResult<Expr> {
+        let expr = self.or()?;
+
+        if self.match_tokens(&[TokenType::QuestionMark]).is_some() {
+            let when_true = self.expression()?;
+            self.consume(TokenType::Colon, "missing : in conditional expression")?;
+            // recurse for right associativity -e.g. `a ? b : c ? d : e`
+            let when_false = self.conditional()?;
+
+            return Ok(Expr::new_conditional(expr, when_true, when_false));
+        }
+
+        Ok(expr)
+    }
+
     /// equality → comparison ( ( "!=" | "==" ) comparison )* ;
     fn equality(&mut self) -> ParserResult<Expr> {
         let mut expr = self.comparison()?;
@@ -185,7 +284,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    /// unary → ( "!" | "-" ) unary | primary ;
+    /// unary → ( "!" | "-" ) unary | call ;
     fn unary(&mut self) -> ParserResult<Expr> {
         match self.match_tokens(&[TokenType::Bang, TokenType::Minus]) {
             Some(token) => {
@@ -194,50 +293,263 @@ impl<'a> Parser<'a> {
 
                 Ok(Expr::new_unary(operator, right))
             }
-            None => self.primary(),
+            None => self.call(),
+        }
+    }
+
+    /// call → primary ( "(" arguments? ")" | "." IDENTIFIER )* ;
+    fn call(&mut self) -> ParserResult<Expr> {
+        let mut expr = self.primary()?;
+
+        while let Some(token) =
+            self.match_tokens(&[TokenType::LeftParen, TokenType::Dot, TokenType::LeftBracket])
+        {
+            expr = match token.token_type {
+                TokenType::LeftParen => self.finish_call(expr)?,
+                TokenType::LeftBracket => {
+                    let index = self.expression()?;
+                    self.consume(TokenType::RightBracket, "missing ] after index")?;
+                    let span = self.span_from(expr.span);
+                    Expr::new_index(expr, index, span)
+                }
+                _ => {
+                    let name = self
+                        .consume(TokenType::Identifier, "missing property name after .")?
+                        .clone();
+                    Expr::new_get(expr, name)
+                }
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// `"a ${b} c"` is scanned as `Interpolation("a ")`, the tokens of `b` and `String(" c")`, and
+    /// parsed into the parts `"a "`, `b` and `" c"`. Empty string parts are left out.
+    fn interpolation(&mut self, first: Token) -> ParserResult<Expr> {
+        let start = first.span;
+        let mut parts = Vec::new();
+        let mut previous = first;
+
+        loop {
+            if !previous.lexeme.is_empty() {
+                parts.push(Expr::new(
+                    ExprKind::StringLiteral(previous.lexeme),
+                    previous.span,
+                ));
+            }
+
+            parts.push(self.expression()?);
+
+            let Some(part) = self.match_tokens(&[TokenType::Interpolation, TokenType::String])
+            else {
+                return Err(self.expected_token(
+                    TokenType::RightBrace,
+                    "missing } after interpolated expression",
+                ));
+            };
+
+            if part.token_type == TokenType::String {
+                if !part.lexeme.is_empty() {
+                    parts.push(Expr::new(ExprKind::StringLiteral(part.lexeme), part.span));
+                }
+                return Ok(Expr::new_interpolation(parts, start.to(part.span)));
+            }
+            previous = part;
         }
     }
 
-    /// primary → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" ;
+    /// Comma separated elements up to the closing `]`, which may follow a trailing comma
+    fn list_elements(&mut self) -> ParserResult<Vec<Expr>> {
+        let mut elements = Vec::new();
+
+        while !self.check(&TokenType::RightBracket) {
+            elements.push(self.expression()?);
+
+            if self.match_tokens(&[TokenType::Comma]).is_none() {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RightBracket, "missing ] after list elements")?;
+        Ok(elements)
+    }
+
+    /// Comma separated `key: value` pairs up to the closing `}`, which may follow a trailing comma
+    fn map_entries(&mut self) -> ParserResult<Vec<(Expr, Expr)>> {
+        let mut entries = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) {
+            let key = self.expression()?;
+            self.consume(TokenType::Colon, "missing : after map key")?;
+            let value = self.expression()?;
+            entries.push((key, value));
+
+            if self.match_tokens(&[TokenType::Comma]).is_none() {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "missing } after map entries")?;
+        Ok(entries)
+    }
+
+    /// At the start of a statement, `{` followed by a literal key and `:` is a map -e.g.
+    /// `{"a": 1}["a"];`. Anything else is a block, including `{}`.
+    fn is_map_literal(&self) -> bool {
+        let mut iter = self.iter.clone();
+        if !matches!(iter.next(), Some(token) if token.token_type == TokenType::LeftBrace) {
+            return false;
+        }
+
+        // negative number keys
+        iter.next_if(|token| token.token_type == TokenType::Minus);
+
+        matches!(
+            iter.next().map(|token| &token.token_type),
+            Some(
+                TokenType::String
+                    | TokenType::Number
+                    | TokenType::True
+                    | TokenType::False
+                    | TokenType::Nil
+            )
+        ) && matches!(iter.next(), Some(token) if token.token_type == TokenType::Colon)
+    }
+
+    /// arguments → expression ( "," expression )* ;
+    fn finish_call(&mut self, callee: Expr) -> ParserResult<Expr> {
+        let mut arguments = Vec::new();
+
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let token = self.peek_token();
+                    return Err(ParserError::TooManyArguments { token });
+                }
+                arguments.push(self.expression()?);
+
+                if self.match_tokens(&[TokenType::Comma]).is_none() {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(TokenType::RightParen, "missing ) after arguments")?
+            .clone();
+
+        Ok(Expr::new_call(callee, paren, arguments))
+    }
+
+    /// primary → NUMBER | STRING | "true" | "false" | "nil" | "this" | "(" expression ")"
+    ///         | IDENTIFIER | "super" "." IDENTIFIER | "fun" "(" parameters? ")" block ;
     fn primary(&mut self) -> ParserResult<Expr> {
-        let token = self.advance().ok_or(ParserError::ExpectedExpression)?;
+        // Eof is left alone so that callers can still tell the input is exhausted
+        let Some(token) = self
+            .iter
+            .next_if(|token| token.token_type != TokenType::Eof)
+        else {
+            return Err(ParserError::UnexpectedEof {
+                message: "expected expression",
+                span: self.peek_token().span,
+            });
+        };
+        self.previous = token.span;
 
         match &token.token_type {
-            TokenType::True => Ok(Expr::BoolLiteral(true)),
-            TokenType::False => Ok(Expr::BoolLiteral(false)),
-            TokenType::Nil => Ok(Expr::Nil),
+            TokenType::True => Ok(Expr::new(ExprKind::BoolLiteral(true), token.span)),
+            TokenType::False => Ok(Expr::new(ExprKind::BoolLiteral(false), token.span)),
+            TokenType::Nil => Ok(Expr::new(ExprKind::Nil, token.span)),
             TokenType::Number => token
                 .lexeme
                 .parse::<f64>()
                 .map_err(|_| ParserError::InvalidNumber {
                     token: token.clone(),
                 })
-                .map(Expr::NumberLiteral),
-            TokenType::String => {
-                // String lexeme includes quotes, strip them
-                let content = token
-                    .lexeme
-                    .strip_prefix('"')
-                    .and_then(|s| s.strip_suffix('"'))
-                    .unwrap_or(&token.lexeme);
-                Ok(Expr::StringLiteral(content.to_string()))
+                .map(|value| Expr::new(ExprKind::NumberLiteral(value), token.span)),
+            TokenType::String => Ok(Expr::new(
+                ExprKind::StringLiteral(token.lexeme.clone()),
+                token.span,
+            )),
+            TokenType::Interpolation => {
+                let token = token.clone();
+                self.interpolation(token)
             }
 
             TokenType::LeftParen => {
+                let start = token.span;
                 let expr = self.expression()?; // must be called before consuming
                 self.consume(TokenType::RightParen, "missing ) after expression.")?;
-                Ok(Expr::new_grouping(expr))
+                Ok(Expr::new_grouping(expr, self.span_from(start)))
+            }
+            TokenType::LeftBracket => {
+                let start = token.span;
+                let elements = self.list_elements()?;
+                Ok(Expr::new_list(elements, self.span_from(start)))
+            }
+            // blocks are statements, so a brace in an expression is always a map
+            TokenType::LeftBrace => {
+                let start = token.span;
+                let entries = self.map_entries()?;
+                Ok(Expr::new_map(entries, self.span_from(start)))
+            }
+            TokenType::Identifier => Ok(Expr::new_variable(token.clone())),
+            TokenType::Fun => {
+                let start = token.span;
+                let (params, body) = self.function_body()?;
+                Ok(Expr::new_function(params, body, self.span_from(start)))
+            }
+            TokenType::This => Ok(Expr::new_this(token.clone())),
+            TokenType::Super => {
+                let keyword = token.clone();
+                self.consume(TokenType::Dot, "missing . after super")?;
+                let method = self
+                    .consume(TokenType::Identifier, "missing superclass method name")?
+                    .clone();
+                Ok(Expr::new_super(keyword, method))
+            }
+
+            // error productions for binary operators with no left-hand operand -e.g. `== 3`
+            TokenType::Or
+            | TokenType::And
+            | TokenType::EqualEqual
+            | TokenType::BangEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::Plus
+            | TokenType::Slash
+            | TokenType::Star => {
+                let operator = token.clone();
+                self.missing_left_operand(operator)
             }
-            TokenType::Identifier => Ok(Expr::Variable {
-                name: token.clone(),
-            }),
 
-            _ => Err(ParserError::ExpectedExpression),
+            _ => Err(ParserError::ExpectedExpression { span: token.span }),
         }
     }
 
+    /// Parse and discard the right-hand operand at the operator's precedence so that parsing can
+    /// carry on from a sensible place, then report the missing left-hand operand.
+    fn missing_left_operand(&mut self, operator: Token) -> ParserResult<Expr> {
+        match operator.token_type {
+            TokenType::Or => self.and()?,
+            TokenType::And => self.equality()?,
+            TokenType::EqualEqual | TokenType::BangEqual => self.comparison()?,
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => self.term()?,
+            TokenType::Plus => self.factor()?,
+            _ => self.unary()?,
+        };
+
+        Err(ParserError::MissingLeftOperand { operator })
+    }
+
     // ifStmt → "if" "(" expression ")" statement | ( "else" statement )? ;
-    fn if_stmt(&mut self) -> ParserResult<Stmt> {
+    fn if_stmt(&mut self, start: Span) -> ParserResult<Stmt> {
         self.consume(TokenType::LeftParen, "missing ( after if")?;
         let condition = self.expression()?;
 
@@ -249,15 +561,17 @@ impl<'a> Parser<'a> {
             false => None,
         };
 
-        Ok(Stmt::new_conditional(condition, when_true, when_false))
+        Ok(Stmt::new_conditional(
+            condition,
+            when_true,
+            when_false,
+            self.span_from(start),
+        ))
     }
 
     fn consume(&mut self, token_type: TokenType, message: &'static str) -> ParserResult<&Token> {
         if !self.check(&token_type) {
-            return Err(ParserError::ExpectedToken {
-                token_type,
-                message,
-            });
+            return Err(self.expected_token(token_type, message));
         }
 
         // TODO: improve this. Maybe some kind of map?
@@ -265,54 +579,142 @@ impl<'a> Parser<'a> {
         Ok(self.advance().expect(message))
     }
 
+    /// The error for a missing token. Running out of input is told apart so that the REPL can ask
+    /// for more instead of reporting it.
+    fn expected_token(&mut self, token_type: TokenType, message: &'static str) -> ParserError {
+        // point right after the last token -e.g. where a missing `;` should go
+        let span = self.previous.after();
+        match self.is_eof() {
+            true => ParserError::UnexpectedEof { message, span },
+            false => ParserError::ExpectedToken {
+                token_type,
+                message,
+                span,
+            },
+        }
+    }
+
     fn statement(&mut self) -> ParserResult<Stmt> {
         // FIXME: use match instead of this mess
-        if self.match_tokens(&[TokenType::Print]).is_some() {
-            return self.print_stmt();
+        if let Some(keyword) = self.match_tokens(&[TokenType::Print]) {
+            return self.print_stmt(keyword.span);
         }
-        if self.match_tokens(&[TokenType::Continue]).is_some() {
-            return self.continue_stmt();
+        if let Some(keyword) = self.match_tokens(&[TokenType::Return]) {
+            return self.return_stmt(keyword);
         }
-        if self.match_tokens(&[TokenType::Break]).is_some() {
-            return self.break_stmt();
+        if let Some(keyword) = self.match_tokens(&[TokenType::Continue]) {
+            return self.continue_stmt(keyword);
         }
-        if self.match_tokens(&[TokenType::For]).is_some() {
-            return self.for_stmt();
+        if let Some(keyword) = self.match_tokens(&[TokenType::Break]) {
+            return self.break_stmt(keyword);
         }
-        if self.match_tokens(&[TokenType::While]).is_some() {
-            return self.while_stmt();
+        if self.check(&TokenType::Identifier) && self.check_next(&TokenType::Colon) {
+            return self.labeled_stmt();
         }
-        if self.match_tokens(&[TokenType::LeftBrace]).is_some() {
-            return self.block_stmt();
+        if let Some(token) = self.match_tokens(&[
+            TokenType::For,
+            TokenType::While,
+            TokenType::Do,
+            TokenType::Loop,
+        ]) {
+            let start = token.span;
+            return self.loop_stmt(token, None, start);
+        }
+        // `{` starts a block unless it is clearly a map literal
+        if self.is_map_literal() {
+            return self.expression_stmt();
         }
-        if self.match_tokens(&[TokenType::If]).is_some() {
-            return self.if_stmt();
+        if let Some(token) = self.match_tokens(&[TokenType::LeftBrace]) {
+            return self.block_stmt(token.span);
+        }
+        if let Some(token) = self.match_tokens(&[TokenType::If]) {
+            return self.if_stmt(token.span);
         }
         self.expression_stmt()
     }
 
-    fn print_stmt(&mut self) -> ParserResult<Stmt> {
+    fn print_stmt(&mut self, start: Span) -> ParserResult<Stmt> {
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "missing ; after expression")?;
 
-        Ok(Stmt::Print(expr))
+        Ok(Stmt::new(StmtKind::Print(expr), self.span_from(start)))
     }
 
-    fn while_stmt(&mut self) -> ParserResult<Stmt> {
+    /// `label: loop`, where the loop is any of the loop statements
+    fn labeled_stmt(&mut self) -> ParserResult<Stmt> {
+        let label = self.peek_token();
+        self.advance();
+        self.advance(); // :
+
+        match self.match_tokens(&[
+            TokenType::For,
+            TokenType::While,
+            TokenType::Do,
+            TokenType::Loop,
+        ]) {
+            Some(keyword) => {
+                let start = label.span;
+                self.loop_stmt(keyword, Some(label), start)
+            }
+            None => Err(ParserError::LabelWithoutLoop { label }),
+        }
+    }
+
+    fn loop_stmt(
+        &mut self,
+        keyword: Token,
+        label: Option<Token>,
+        start: Span,
+    ) -> ParserResult<Stmt> {
+        match keyword.token_type {
+            TokenType::For => self.for_stmt(label, start),
+            TokenType::While => self.while_stmt(label, start),
+            TokenType::Do => self.do_while_stmt(label, start),
+            _ => {
+                let body = self.statement()?;
+                Ok(Stmt::new_loop(body, label, self.span_from(start)))
+            }
+        }
+    }
+
+    fn while_stmt(&mut self, label: Option<Token>, start: Span) -> ParserResult<Stmt> {
         self.consume(TokenType::LeftParen, "missing ( after while")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "missing } after while conditon")?;
         let body = self.statement()?;
 
-        Ok(Stmt::new_while(condition, body))
+        Ok(Stmt::new_while(
+            condition,
+            body,
+            label,
+            self.span_from(start),
+        ))
+    }
+
+    fn do_while_stmt(&mut self, label: Option<Token>, start: Span) -> ParserResult<Stmt> {
+        let body = self.statement()?;
+        self.consume(TokenType::While, "missing while after do body")?;
+        self.consume(TokenType::LeftParen, "missing ( after while")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "missing ) after while condition")?;
+        self.consume(TokenType::Semicolon, "missing ; after do-while")?;
+
+        Ok(Stmt::new_do_while(
+            body,
+            condition,
+            label,
+            self.span_from(start),
+        ))
     }
 
     // forStmt → "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement ;
-    fn for_stmt(&mut self) -> ParserResult<Stmt> {
+    fn for_stmt(&mut self, label: Option<Token>, start: Span) -> ParserResult<Stmt> {
         self.consume(TokenType::LeftParen, "missing ( after for")?;
 
         let initializer = match self.match_tokens(&[TokenType::Var, TokenType::Semicolon]) {
-            Some(token) if token.token_type == TokenType::Var => Some(self.var_declaration()?),
+            Some(token) if token.token_type == TokenType::Var => {
+                Some(self.var_declaration(token.span)?)
+            }
             Some(token) if token.token_type == TokenType::Semicolon => None,
             _ => Some(self.expression_stmt()?),
         };
@@ -331,49 +733,180 @@ impl<'a> Parser<'a> {
         self.consume(TokenType::RightParen, "missing ) after for conditon")?;
         let body = self.statement()?;
 
-        Ok(Stmt::new_for(initializer, condition, increment, body))
+        Ok(Stmt::new_for(
+            initializer,
+            condition,
+            increment,
+            body,
+            label,
+            self.span_from(start),
+        ))
+    }
+
+    fn block_stmt(&mut self, start: Span) -> ParserResult<Stmt> {
+        let stmts = self.block()?;
+        Ok(Stmt::new(StmtKind::Block(stmts), self.span_from(start)))
     }
 
-    fn block_stmt(&mut self) -> ParserResult<Stmt> {
+    /// Parse the declarations of a block. The opening `{` must already be consumed.
+    fn block(&mut self) -> ParserResult<Vec<Stmt>> {
         let mut stmts: Vec<Stmt> = Vec::new();
 
         while !self.check(&TokenType::RightBrace) && !self.is_eof() {
-            stmts.push(self.declaration()?);
+            if let Some(stmt) = self.declaration_or_synchronise() {
+                stmts.push(stmt);
+            }
         }
 
         self.consume(TokenType::RightBrace, "missing } after block")?;
 
-        Ok(Stmt::Block(stmts))
+        Ok(stmts)
     }
 
     fn expression_stmt(&mut self) -> ParserResult<Stmt> {
         let expr = self.expression()?;
-        self.consume(TokenType::Semicolon, "missing ; after expression")?;
+        let start = expr.span;
+
+        // nested statements start after the top-level statement that contains them
+        let is_trailing =
+            self.trailing_expression && start.start == self.statement_start && self.is_eof();
+        if !is_trailing {
+            self.consume(TokenType::Semicolon, "missing ; after expression")?;
+        }
 
-        Ok(Stmt::Expression(expr))
+        Ok(Stmt::new(StmtKind::Expression(expr), self.span_from(start)))
     }
 
-    fn continue_stmt(&mut self) -> ParserResult<Stmt> {
+    fn continue_stmt(&mut self, keyword: Token) -> ParserResult<Stmt> {
+        let label = self.match_tokens(&[TokenType::Identifier]);
         self.consume(TokenType::Semicolon, "missing ; after continue")?;
 
-        Ok(Stmt::Continue)
+        let span = self.span_from(keyword.span);
+        Ok(Stmt::new_continue(keyword, label, span))
     }
 
-    fn break_stmt(&mut self) -> ParserResult<Stmt> {
+    fn break_stmt(&mut self, keyword: Token) -> ParserResult<Stmt> {
+        let label = self.match_tokens(&[TokenType::Identifier]);
         self.consume(TokenType::Semicolon, "missing ; after break")?;
 
-        Ok(Stmt::Break)
+        let span = self.span_from(keyword.span);
+        Ok(Stmt::new_break(keyword, label, span))
+    }
+
+    // returnStmt → "return" expression? ";" ;
+    fn return_stmt(&mut self, keyword: Token) -> ParserResult<Stmt> {
+        let value = match self.check(&TokenType::Semicolon) {
+            false => Some(self.expression()?),
+            true => None,
+        };
+
+        self.consume(TokenType::Semicolon, "missing ; after return value")?;
+
+        let span = self.span_from(keyword.span);
+        Ok(Stmt::new(StmtKind::Return { keyword, value }, span))
     }
 
     fn declaration(&mut self) -> ParserResult<Stmt> {
-        if self.match_tokens(&[TokenType::Var]).is_some() {
-            self.var_declaration()
+        // anonymous functions are expressions and are handled by primary
+        if self.check(&TokenType::Fun) && self.check_next(&TokenType::Identifier) {
+            let start = self.peek_token().span;
+            self.advance();
+            let function = self.function()?;
+            Ok(Stmt::new(
+                StmtKind::Function(function),
+                self.span_from(start),
+            ))
+        } else if let Some(token) = self.match_tokens(&[TokenType::Class]) {
+            self.class_declaration(token.span)
+        } else if let Some(token) = self.match_tokens(&[TokenType::Var]) {
+            self.var_declaration(token.span)
         } else {
             self.statement()
         }
     }
 
-    fn var_declaration(&mut self) -> ParserResult<Stmt> {
+    // function → IDENTIFIER "(" parameters? ")" block ;
+    // parameters → IDENTIFIER ( "," IDENTIFIER )* ;
+    fn function(&mut self) -> ParserResult<Rc<FunctionDecl>> {
+        let name = self
+            .consume(TokenType::Identifier, "missing function name")?
+            .clone();
+
+        let (params, body) = self.function_body()?;
+
+        Ok(Rc::new(FunctionDecl {
+            name: Some(name),
+            params,
+            body,
+        }))
+    }
+
+    // classDecl → "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+    fn class_declaration(&mut self, start: Span) -> ParserResult<Stmt> {
+        let name = self
+            .consume(TokenType::Identifier, "missing class name")?
+            .clone();
+
+        let superclass = match self.match_tokens(&[TokenType::Less]) {
+            Some(_) => {
+                let name = self
+                    .consume(TokenType::Identifier, "missing superclass name")?
+                    .clone();
+                Some(Expr::new_variable(name))
+            }
+            None => None,
+        };
+
+        self.consume(TokenType::LeftBrace, "missing { before class body")?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_eof() {
+            methods.push(self.function()?);
+        }
+
+        self.consume(TokenType::RightBrace, "missing } after class body")?;
+
+        Ok(Stmt::new(
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            },
+            self.span_from(start),
+        ))
+    }
+
+    /// Parse everything after the function name -or after `fun` for anonymous functions
+    fn function_body(&mut self) -> ParserResult<(Vec<Token>, Vec<Stmt>)> {
+        self.consume(TokenType::LeftParen, "missing ( before parameters")?;
+
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    let token = self.peek_token();
+                    return Err(ParserError::TooManyArguments { token });
+                }
+                params.push(
+                    self.consume(TokenType::Identifier, "missing parameter name")?
+                        .clone(),
+                );
+
+                if self.match_tokens(&[TokenType::Comma]).is_none() {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::RightParen, "missing ) after parameters")?;
+        self.consume(TokenType::LeftBrace, "missing { before function body")?;
+
+        let body = self.block()?;
+
+        Ok((params, body))
+    }
+
+    fn var_declaration(&mut self, start: Span) -> ParserResult<Stmt> {
         let name = self
             .consume(TokenType::Identifier, "missing variable name.")?
             .clone();
@@ -388,6 +921,164 @@ impl<'a> Parser<'a> {
             "missing ; after variable declaration.",
         )?;
 
-        Ok(Stmt::Var { name, initializer })
+        Ok(Stmt::new(
+            StmtKind::Var { name, initializer },
+            self.span_from(start),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LoxConfig,
+        frontend::{FileId, Scanner},
+    };
+
+    fn parse(src: &str) -> Result<Vec<Stmt>, Vec<ParserError>> {
+        let mut scanner = Scanner::new(src, FileId::default(), LoxConfig::default());
+        let (tokens, errors) = scanner.scan_tokens();
+        assert!(errors.is_empty());
+        Parser::new(tokens).parse()
+    }
+
+    #[test]
+    fn missing_left_operand() {
+        for (src, lexeme) in [
+            ("== 3;", "=="),
+            ("* x;", "*"),
+            ("1 + / 2;", "/"),
+            ("or a;", "or"),
+        ] {
+            match parse(src).map_err(|errors| errors.into_iter().next()) {
+                Err(Some(ParserError::MissingLeftOperand { operator })) => {
+                    assert_eq!(operator.lexeme, lexeme, "{src}")
+                }
+                other => panic!("{src}: expected MissingLeftOperand, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let errors = parse("var = 1;\nprint * 2;\n{ var = 3; print 4; }\nprint 5")
+            .expect_err("invalid syntax");
+
+        assert_eq!(errors.len(), 4, "{errors:#?}");
+        assert!(matches!(errors[0], ParserError::ExpectedToken { .. }));
+        assert!(matches!(errors[1], ParserError::MissingLeftOperand { .. }));
+        assert!(matches!(errors[2], ParserError::ExpectedToken { .. }));
+        assert!(matches!(errors[3], ParserError::UnexpectedEof { .. }));
+    }
+
+    #[test]
+    fn unexpected_end_of_input() {
+        for src in [
+            "while (x < 3) {",
+            "print (1 +",
+            "if (a) print 1; else",
+            "f(1, 2",
+        ] {
+            let errors = parse(src).expect_err("incomplete syntax");
+            assert!(
+                matches!(&errors[..], [ParserError::UnexpectedEof { .. }]),
+                "{src}: {errors:?}"
+            );
+        }
+
+        let errors = parse("print 1 print 2;").expect_err("invalid syntax");
+        assert!(matches!(&errors[..], [ParserError::ExpectedToken { .. }]));
+    }
+
+    #[test]
+    fn trailing_expression() {
+        let parse_repl = |src| {
+            let mut scanner = Scanner::new(src, FileId::default(), LoxConfig::default());
+            let (tokens, _) = scanner.scan_tokens();
+            Parser::new(tokens).allow_trailing_expression().parse()
+        };
+
+        assert!(parse("1 + 2").is_err());
+        assert!(parse_repl("var a = 1; a + 2").is_ok());
+        assert!(parse_repl("{\"a\": 1}").is_ok());
+
+        // only a top-level expression can leave out its `;`
+        for src in ["1 2", "if (a) b", "{ a }", "print a"] {
+            assert!(parse_repl(src).is_err(), "{src}");
+        }
+    }
+
+    /// The expression of each expression statement in `src`, printed
+    fn print_expressions(src: &str) -> Vec<String> {
+        parse(src)
+            .unwrap()
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Expression(expr) => Expr::print(expr),
+                _ => panic!("expected an expression statement"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(
+            print_expressions("a ? b : c ? d : e; a ? b ? c : d : e;"),
+            ["(? a b (? c d e))", "(? a (? b c d) e)"]
+        );
+        // binds looser than `or` and tighter than assignment
+        assert_eq!(
+            print_expressions("x = a or b ? c : d; a ? b : c or d; a ? x = b : c;"),
+            ["x (? (or a b) c d)", "(? a b (or c d))", "(? a x b c)"]
+        );
+
+        // only the middle operand can be an assignment without parentheses
+        let errors = parse("a ? b : c = d;").expect_err("invalid syntax");
+        assert!(matches!(
+            &errors[..],
+            [ParserError::InvalidAssignmentTarget { .. }]
+        ));
+    }
+
+    #[test]
+    fn unary_minus_is_not_an_error_production() {
+        assert!(parse("-1;").is_ok());
+    }
+
+    #[test]
+    fn labels_need_a_loop() {
+        assert!(parse("outer: for (;;) { inner: while (true) break outer; }").is_ok());
+        assert!(parse("a: do print 1; while (false); b: loop break b;").is_ok());
+
+        let errors = parse("a: print 1; print 2;").expect_err("invalid syntax");
+        assert!(matches!(
+            &errors[..],
+            [ParserError::LabelWithoutLoop { label }] if label.lexeme == "a"
+        ));
+    }
+
+    #[test]
+    fn maps_and_blocks() {
+        let stmts = parse("{\"a\": 1}; {} { a: loop break a; } var m = {a: 1};").unwrap();
+        let kinds: Vec<&str> = stmts
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Expression(_) => "expression",
+                StmtKind::Block(_) => "block",
+                StmtKind::Var { .. } => "var",
+                _ => "other",
+            })
+            .collect();
+
+        assert_eq!(kinds, ["expression", "block", "block", "var"]);
+    }
+
+    #[test]
+    fn interpolation_parts() {
+        assert_eq!(
+            print_expressions(r#""a ${b} c ${d}"; "${"x"}${y + 1}";"#),
+            ["(interpolate a  b  c  d)", "(interpolate x (+ y 1))"]
+        );
     }
 }
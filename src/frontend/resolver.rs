@@ -0,0 +1,579 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::token::Token;
+use crate::LoxConfig;
+use crate::ast::{Depth, Expr, ExprKind, FunctionDecl, Stmt, StmtKind};
+use crate::frontend::ResolverError;
+
+/// Whether a variable's initializer has finished resolving
+type Scope = HashMap<String, bool>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Static pass that runs between the parser and the interpreter
+///
+/// It reports mistakes that can be found without running the program and records, on every
+/// variable use, how many scopes away its declaration is. The scopes pushed here must mirror the
+/// ones the interpreter creates at runtime, otherwise the recorded depths will point at the wrong
+/// scope. Globals are not tracked and are looked up by name.
+pub struct Resolver {
+    scopes: Vec<Scope>,
+    current_function: FunctionType,
+    current_class: ClassType,
+
+    /// Labels of the loops enclosing the current statement within the current function, innermost
+    /// last
+    loops: Vec<Option<String>>,
+
+    /// Errors found so far. Resolving carries on after them so that they can all be reported at
+    /// once.
+    errors: Vec<ResolverError>,
+
+    config: LoxConfig,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new(LoxConfig::default())
+    }
+}
+
+impl Resolver {
+    pub fn new(config: LoxConfig) -> Self {
+        Self {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loops: Vec::new(),
+            errors: Vec::new(),
+            config,
+        }
+    }
+
+    pub fn resolve(&mut self, stmts: &[Stmt]) -> Result<(), Vec<ResolverError>> {
+        self.resolve_stmts(stmts);
+
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(std::mem::take(&mut self.errors)),
+        }
+    }
+
+    fn resolve_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts.iter() {
+            self.resolve_stmt(stmt);
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Print(expr) | StmtKind::Expression(expr) => self.resolve_expr(expr),
+            StmtKind::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(expr) = initializer {
+                    self.resolve_expr(expr);
+                }
+                self.define(name);
+            }
+            StmtKind::Block(stmts) => {
+                self.begin_scope();
+                self.resolve_stmts(stmts);
+                self.end_scope();
+            }
+            StmtKind::Conditional {
+                condition,
+                when_true,
+                when_false,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(when_true);
+                if let Some(stmt) = when_false {
+                    self.resolve_stmt(stmt);
+                }
+            }
+            StmtKind::While {
+                condition,
+                body,
+                label,
+            }
+            | StmtKind::DoWhile {
+                body,
+                condition,
+                label,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_loop_body(body, label);
+            }
+            StmtKind::Loop { body, label } => self.resolve_loop_body(body, label),
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                label,
+            } => {
+                // the interpreter captures the initializer in its own scope
+                self.begin_scope();
+                self.resolve_for(initializer, condition, increment, body, label);
+                self.end_scope();
+            }
+            StmtKind::Continue {
+                keyword,
+                label,
+                depth,
+            }
+            | StmtKind::Break {
+                keyword,
+                label,
+                depth,
+            } => match self.loop_control_depth(keyword, label) {
+                Ok(position) => depth.set(Some(position)),
+                Err(e) => self.errors.push(e),
+            },
+            StmtKind::Function(declaration) => {
+                if let Some(name) = &declaration.name {
+                    // declared and defined straight away so that functions can recurse
+                    self.declare(name);
+                    self.define(name);
+                }
+                self.resolve_function(declaration, FunctionType::Function);
+            }
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                self.declare(name);
+                self.define(name);
+
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+                self.resolve_class(name, superclass, methods);
+                self.current_class = enclosing_class;
+            }
+            StmtKind::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.errors.push(ResolverError::TopLevelReturn {
+                        token: keyword.clone(),
+                    });
+                }
+
+                if let Some(expr) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.errors.push(ResolverError::InitializerReturn {
+                            token: keyword.clone(),
+                        });
+                    }
+                    self.resolve_expr(expr);
+                }
+            }
+        };
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            // literals have nothing to resolve
+            ExprKind::BoolLiteral(_)
+            | ExprKind::NumberLiteral(_)
+            | ExprKind::StringLiteral(_)
+            | ExprKind::Nil => {}
+            ExprKind::Grouping(expr) => self.resolve_expr(expr),
+            ExprKind::Unary { right, .. } => self.resolve_expr(right),
+            ExprKind::Binary { left, right, .. } | ExprKind::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            ExprKind::Variable { name, depth } => {
+                let declared_but_undefined = self
+                    .scopes
+                    .last()
+                    .is_some_and(|scope| scope.get(&name.lexeme) == Some(&false));
+
+                if declared_but_undefined {
+                    self.errors.push(ResolverError::ReadInOwnInitializer {
+                        token: name.clone(),
+                    });
+                }
+
+                self.resolve_local(name, depth);
+            }
+            ExprKind::Assignment { name, value, depth } => {
+                self.resolve_expr(value);
+                self.resolve_local(name, depth);
+            }
+            ExprKind::Conditional {
+                condition,
+                when_true,
+                when_false,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(when_true);
+                self.resolve_expr(when_false);
+            }
+            ExprKind::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expr(callee);
+                for argument in arguments {
+                    self.resolve_expr(argument);
+                }
+            }
+            ExprKind::Function(declaration) => {
+                self.resolve_function(declaration, FunctionType::Function);
+            }
+            ExprKind::Get { object, .. } => self.resolve_expr(object),
+            ExprKind::List(elements) | ExprKind::Interpolation(elements) => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            ExprKind::Index { object, index } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            ExprKind::SetIndex {
+                object,
+                index,
+                value,
+            } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            }
+            ExprKind::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            ExprKind::This { keyword, depth } => match self.current_class {
+                ClassType::None => self.errors.push(ResolverError::ThisOutsideClass {
+                    token: keyword.clone(),
+                }),
+                _ => self.resolve_local(keyword, depth),
+            },
+            ExprKind::Super { keyword, depth, .. } => match self.current_class {
+                ClassType::None => self.errors.push(ResolverError::SuperOutsideClass {
+                    token: keyword.clone(),
+                }),
+                ClassType::Class => self.errors.push(ResolverError::SuperWithoutSuperclass {
+                    token: keyword.clone(),
+                }),
+                ClassType::Subclass => self.resolve_local(keyword, depth),
+            },
+        };
+    }
+
+    fn resolve_for(
+        &mut self,
+        initializer: &Option<Box<Stmt>>,
+        condition: &Option<Expr>,
+        increment: &Option<Expr>,
+        body: &Stmt,
+        label: &Option<Token>,
+    ) {
+        if let Some(initializer) = initializer {
+            self.resolve_stmt(initializer);
+        }
+        if let Some(condition) = condition {
+            self.resolve_expr(condition);
+        }
+        if let Some(increment) = increment {
+            self.resolve_expr(increment);
+        }
+
+        self.resolve_loop_body(body, label)
+    }
+
+    /// How many loops out from the innermost one `break` or `continue` jumps
+    fn loop_control_depth(
+        &self,
+        keyword: &Token,
+        label: &Option<Token>,
+    ) -> Result<usize, ResolverError> {
+        if !self.config.loop_control {
+            return Err(ResolverError::LoopControlDisabled {
+                token: keyword.clone(),
+            });
+        }
+        if self.loops.is_empty() {
+            return Err(ResolverError::OutsideLoop {
+                token: keyword.clone(),
+            });
+        }
+
+        match label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rev()
+                .position(|name| name.as_ref() == Some(&label.lexeme))
+                .ok_or_else(|| ResolverError::UndefinedLabel {
+                    token: label.clone(),
+                }),
+            None => Ok(0),
+        }
+    }
+
+    fn resolve_loop_body(&mut self, body: &Stmt, label: &Option<Token>) {
+        self.loops
+            .push(label.as_ref().map(|label| label.lexeme.clone()));
+        self.resolve_stmt(body);
+        self.loops.pop();
+    }
+
+    fn resolve_function(&mut self, declaration: &FunctionDecl, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        // `break` and `continue` can't jump out of a function body
+        let enclosing_loops = std::mem::take(&mut self.loops);
+
+        // parameters and body share the same scope at runtime
+        self.begin_scope();
+        for param in &declaration.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_stmts(&declaration.body);
+        self.end_scope();
+
+        self.loops = enclosing_loops;
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_class(
+        &mut self,
+        name: &Token,
+        superclass: &Option<Expr>,
+        methods: &[Rc<FunctionDecl>],
+    ) {
+        if let Some(superclass) = superclass {
+            if let ExprKind::Variable {
+                name: superclass_name,
+                ..
+            } = &superclass.kind
+                && superclass_name.lexeme == name.lexeme
+            {
+                self.errors.push(ResolverError::InheritsFromItself {
+                    token: superclass_name.clone(),
+                });
+            }
+
+            self.current_class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+
+            self.begin_scope();
+            self.define_name("super");
+        }
+
+        self.begin_scope();
+        self.define_name("this");
+
+        for method in methods {
+            let function_type = match method.name.as_ref() {
+                Some(name) if name.lexeme == "init" => FunctionType::Initializer,
+                _ => FunctionType::Method,
+            };
+            self.resolve_function(method, function_type);
+        }
+
+        self.end_scope();
+        if superclass.is_some() {
+            self.end_scope();
+        }
+    }
+
+    /// Record how far away the variable is declared. Variables that aren't found are assumed to be
+    /// global and are left unresolved.
+    fn resolve_local(&mut self, name: &Token, depth: &Depth) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme));
+
+        depth.set(found);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Scope::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            // globals can be redeclared
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.errors.push(ResolverError::AlreadyDeclared {
+                token: name.clone(),
+            });
+            return;
+        }
+
+        scope.insert(name.lexeme.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        self.define_name(&name.lexeme);
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{FileId, Parser, Scanner};
+
+    /// The statements of `src`, and every error found while resolving them
+    fn resolve(src: &str) -> (Vec<Stmt>, Vec<ResolverError>) {
+        let mut scanner = Scanner::new(src, FileId::default(), LoxConfig::default());
+        let (tokens, errors) = scanner.scan_tokens();
+        assert!(errors.is_empty());
+        let stmts = Parser::new(tokens).parse().expect("valid syntax");
+        let errors = Resolver::default()
+            .resolve(&stmts)
+            .err()
+            .unwrap_or_default();
+        (stmts, errors)
+    }
+
+    #[test]
+    fn read_in_own_initializer() {
+        let (_, errors) = resolve("var a = 1; { var a = a; }");
+        assert!(matches!(
+            &errors[..],
+            [ResolverError::ReadInOwnInitializer { .. }]
+        ));
+    }
+
+    #[test]
+    fn redeclare_local() {
+        let (_, errors) = resolve("{ var a = 1; var a = 2; }");
+        assert!(matches!(
+            &errors[..],
+            [ResolverError::AlreadyDeclared { .. }]
+        ));
+
+        // globals can be redeclared
+        let (_, errors) = resolve("var a = 1; var a = 2;");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn loop_control_outside_loop() {
+        let (_, errors) = resolve("break;");
+        assert!(matches!(&errors[..], [ResolverError::OutsideLoop { .. }]));
+
+        let (_, errors) = resolve("while (true) { fun f() { continue; } }");
+        assert!(matches!(&errors[..], [ResolverError::OutsideLoop { .. }]));
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let (_, errors) = resolve("return 1; { var a = 1; var a = 2; } this;");
+        assert!(
+            matches!(
+                &errors[..],
+                [
+                    ResolverError::TopLevelReturn { .. },
+                    ResolverError::AlreadyDeclared { .. },
+                    ResolverError::ThisOutsideClass { .. },
+                ]
+            ),
+            "{errors:#?}"
+        );
+    }
+
+    #[test]
+    fn loop_labels() {
+        let (stmts, errors) = resolve("a: while (true) { b: loop { for (;;) continue a; } }");
+        assert!(errors.is_empty());
+
+        let StmtKind::While { body, .. } = &stmts[0].kind else {
+            panic!("expected a while loop")
+        };
+        let StmtKind::Block(body) = &body.kind else {
+            panic!("expected a block")
+        };
+        let StmtKind::Loop { body, .. } = &body[0].kind else {
+            panic!("expected a loop")
+        };
+        let StmtKind::Block(body) = &body.kind else {
+            panic!("expected a block")
+        };
+        let StmtKind::For { body, .. } = &body[0].kind else {
+            panic!("expected a for loop")
+        };
+        let StmtKind::Continue { depth, .. } = &body.kind else {
+            panic!("expected continue")
+        };
+        assert_eq!(depth.get(), Some(2));
+
+        let (_, errors) = resolve("a: while (true) break b;");
+        assert!(matches!(
+            &errors[..],
+            [ResolverError::UndefinedLabel { .. }]
+        ));
+
+        // labels don't reach into function bodies
+        let (_, errors) = resolve("a: while (true) { fun f() { while (true) break a; } }");
+        assert!(matches!(
+            &errors[..],
+            [ResolverError::UndefinedLabel { .. }]
+        ));
+    }
+
+    #[test]
+    fn records_depth() {
+        let (stmts, errors) = resolve("var g; { var a; { a; g; } }");
+        assert!(errors.is_empty());
+
+        let StmtKind::Block(outer) = &stmts[1].kind else {
+            panic!("expected a block")
+        };
+        let StmtKind::Block(inner) = &outer[1].kind else {
+            panic!("expected a block")
+        };
+        let depths: Vec<Option<usize>> = inner
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Expression(Expr {
+                    kind: ExprKind::Variable { depth, .. },
+                    ..
+                }) => depth.get(),
+                _ => panic!("expected a variable"),
+            })
+            .collect();
+
+        assert_eq!(depths, vec![Some(1), None]);
+    }
+}
@@ -1,15 +1,32 @@
 use std::{iter::Peekable, str::Chars};
 
-use crate::frontend::{ScannerError, Span};
+use crate::{
+    LoxConfig,
+    frontend::{FileId, ScannerError, Span},
+};
 
 use super::token::{Token, TokenType};
 
 pub type ScannerResult<T> = Result<T, ScannerError>;
 
+/// A `${...}` expression embedded in a string that is still being scanned
+struct Interpolation {
+    /// Braces opened inside the expression and not closed yet. The string resumes at the `}` that
+    /// is reached when this is 0.
+    braces: usize,
+
+    /// Byte offset where the string part before the expression starts
+    start: usize,
+}
+
 pub struct Scanner<'a> {
     tokens: Vec<Token>,
+    errors: Vec<ScannerError>,
     source: &'a str,
 
+    /// The source's id in the `SourceMap`, used in every span
+    file: FileId,
+
     /// A shared iterator over the source
     ///
     /// This iterator will always return None after it's exhausted. This is not always the case
@@ -19,41 +36,65 @@ pub struct Scanner<'a> {
     chars: Peekable<Chars<'a>>,
 
     // source.len() returns usize and these properties are derived from it
-    /// Lexeme start
-    start: usize, // start
+    /// Byte offset where the current lexeme starts
+    start: usize,
 
-    /// Current character position in the source code
+    /// Byte offset of the next character in the source code. Characters can take several bytes,
+    /// so this is not a count of characters.
     current: usize,
 
-    /// Current line in the source code
-    line: usize,
+    /// Strings with an embedded expression being scanned, innermost last
+    interpolations: Vec<Interpolation>,
 
-    /// Current column in the source code
-    col: usize,
+    config: LoxConfig,
 }
 
 impl<'a> Scanner<'a> {
-    pub fn new(source: &'a str) -> Self {
+    pub fn new(source: &'a str, file: FileId, config: LoxConfig) -> Self {
         Self {
             tokens: Vec::new(),
+            errors: Vec::new(),
             source,
+            file,
             chars: source.chars().peekable(),
             start: 0,
             current: 0,
-            line: 1,
-            col: 1,
+            interpolations: Vec::new(),
+            config,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> ScannerResult<&Vec<Token>> {
+    /// Scanning carries on after an error so that every error can be reported at once. The offending
+    /// characters are left out of the tokens.
+    pub fn scan_tokens(&mut self) -> (&Vec<Token>, Vec<ScannerError>) {
         // scan each character
         while let Some(char) = self.advance() {
             // Look at the current and next character
             match (char, self.chars.peek()) {
                 ('(', _) => self.add_token(TokenType::LeftParen),
                 (')', _) => self.add_token(TokenType::RightParen),
-                ('{', _) => self.add_token(TokenType::LeftBrace),
-                ('}', _) => self.add_token(TokenType::RightBrace),
+                ('{', _) => {
+                    if let Some(interpolation) = self.interpolations.last_mut() {
+                        interpolation.braces += 1;
+                    }
+                    self.add_token(TokenType::LeftBrace)
+                }
+                // the end of an embedded expression, the rest of the string follows
+                ('}', _) if self.interpolations.last().is_some_and(|i| i.braces == 0) => {
+                    self.interpolations.pop();
+                    let value = self.empty_interpolation().unwrap_or_default();
+                    if let Err(e) = self.handle_string(value) {
+                        self.errors.push(e);
+                    }
+                }
+                ('}', _) => {
+                    if let Some(interpolation) = self.interpolations.last_mut() {
+                        interpolation.braces -= 1;
+                    }
+                    self.add_token(TokenType::RightBrace)
+                }
+                ('[', _) => self.add_token(TokenType::LeftBracket),
+                (']', _) => self.add_token(TokenType::RightBracket),
                 (',', _) => self.add_token(TokenType::Comma),
                 ('.', _) => self.add_token(TokenType::Dot),
                 ('-', _) => self.add_token(TokenType::Minus),
@@ -79,6 +120,11 @@ impl<'a> Scanner<'a> {
 
                 // slash or comment
                 ('/', Some('/')) => self.handle_comment(),
+                ('/', Some('*')) => {
+                    if let Err(e) = self.handle_block_comment() {
+                        self.errors.push(e);
+                    }
+                }
                 ('/', _) => self.add_token(TokenType::Slash),
 
                 // misc
@@ -89,15 +135,26 @@ impl<'a> Scanner<'a> {
                 (' ', _) => (),
                 ('\t', _) => (),
                 ('\r', _) => (),
-                ('\n', _) => self.increase_line(),
+                ('\n', _) => (),
 
                 // literals
-                ('"', _) => self.handle_string()?,
-                (char, _) if char.is_ascii_digit() => self.handle_number(),
-                (char, _) if Scanner::is_identifier(&char) => self.handle_identifier_and_keywords(),
+                ('"', _) => {
+                    if let Err(e) = self.handle_string(String::new()) {
+                        self.errors.push(e);
+                    }
+                }
+                ('r', Some('"' | '#')) if self.config.extended_strings => {
+                    if let Err(e) = self.handle_raw_string() {
+                        self.errors.push(e);
+                    }
+                }
+                (char, _) if char.is_ascii_digit() => self.handle_number(char),
+                (char, _) if Scanner::is_identifier_start(char) => {
+                    self.handle_identifier_and_keywords()
+                }
 
                 (token, _) => {
-                    return Err(ScannerError::UnknownToken {
+                    self.errors.push(ScannerError::UnknownToken {
                         token,
                         span: self.to_span(),
                     });
@@ -108,18 +165,26 @@ impl<'a> Scanner<'a> {
             self.start = self.current;
         }
 
+        // strings that were still waiting for the `}` of an embedded expression
+        for interpolation in std::mem::take(&mut self.interpolations) {
+            self.errors.push(ScannerError::UnterminatedString {
+                span: self.span_from(interpolation.start),
+            });
+        }
+
         self.add_token(TokenType::Eof);
 
-        Ok(&self.tokens)
+        (&self.tokens, std::mem::take(&mut self.errors))
     }
 
+    /// Span of the current lexeme
     fn to_span(&self) -> Span {
-        Span::new(self.line, self.col)
+        self.span_from(self.start)
     }
 
-    fn increase_line(&mut self) {
-        self.line += 1;
-        self.col = 1;
+    /// Span from the byte at `start` up to the next character
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(self.file, start, self.current)
     }
 
     fn add_token(&mut self, token_type: TokenType) {
@@ -129,71 +194,260 @@ impl<'a> Scanner<'a> {
     }
 
     fn add_token_and_skip(&mut self, token_type: TokenType, skip_chars: usize) {
-        self.add_token(token_type);
-
-        // skip n chars
+        // skip n chars so that they are part of the lexeme
         for _ in 0..skip_chars {
             if self.advance().is_none() {
                 break;
             }
         }
+
+        self.add_token(token_type);
     }
 
-    /// Consume current character. Increases character index if next() is Some(_)
+    /// Consume current character. Moves the byte offset on if next() is Some(_)
     fn advance(&mut self) -> Option<char> {
         let c = self.chars.next();
 
         // prevent out of bound lookups when indexing the source array
-        if c.is_some() {
-            self.current += 1;
-            self.col += 1;
+        if let Some(c) = c {
+            self.current += c.len_utf8();
         }
 
         c
     }
 
-    fn consume_digits(&mut self) {
-        while matches!(self.chars.peek(), Some(c) if c.is_ascii_digit()) {
-            self.advance();
+    /// Consume digits in `radix` onto `digits`. Separators are left out, and must be between two
+    /// digits of the same run.
+    fn consume_digits(&mut self, radix: u32, mut digits: String) -> String {
+        while let Some(&c) = self.chars.peek() {
+            if c.is_digit(radix) {
+                digits.push(c);
+                self.advance();
+            } else if c == '_' {
+                let separator = self.current;
+                self.advance();
+                let before_digit = self.chars.peek().is_some_and(|c| c.is_digit(radix));
+                if digits.is_empty() || !before_digit {
+                    self.errors.push(ScannerError::MisplacedSeparator {
+                        span: self.span_from(separator),
+                    });
+                }
+            } else {
+                break;
+            }
         }
+
+        digits
     }
 
     fn handle_comment(&mut self) {
-        // consume the next character
-        while let Some(c) = self.advance() {
-            if c == '\n' {
-                break;
+        // leave the newline for scan_tokens so that the line count is kept
+        while matches!(self.chars.peek(), Some(c) if *c != '\n') {
+            self.advance();
+        }
+    }
+
+    /// `/* ... */`, which can contain other block comments
+    fn handle_block_comment(&mut self) -> ScannerResult<()> {
+        // consume the `*` of the opening `/*`
+        self.advance();
+        let opening = self.to_span();
+
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.advance(), self.chars.peek()) {
+                (Some('/'), Some('*')) => {
+                    self.advance();
+                    depth += 1;
+                }
+                (Some('*'), Some('/')) => {
+                    self.advance();
+                    depth -= 1;
+                }
+                (Some(_), _) => (),
+                (None, _) => return Err(ScannerError::UnterminatedComment { span: opening }),
             }
         }
+
+        Ok(())
     }
 
-    fn handle_string(&mut self) -> ScannerResult<()> {
-        while let Some(c) = self.chars.peek() {
+    /// Scan the rest of a string after its opening `"`, or after the `}` that ends an embedded
+    /// expression. `value` is what has been scanned of the string so far.
+    fn handle_string(&mut self, mut value: String) -> ScannerResult<()> {
+        loop {
+            let Some(c) = self.advance() else {
+                return Err(ScannerError::UnterminatedString {
+                    span: self.to_span(),
+                });
+            };
+
             match c {
-                // Multi-line comment
-                '\n' => self.increase_line(),
                 '"' => break,
-                _ => (),
-            };
-            _ = self.advance();
+                // the book copies strings as they are written
+                '\\' if self.config.extended_strings => {
+                    if let Some(c) = self.escape() {
+                        value.push(c);
+                    }
+                }
+                '$' if self.config.extended_strings && self.chars.peek() == Some(&'{') => {
+                    self.advance();
+                    self.add_literal_token(TokenType::Interpolation, value);
+                    self.interpolations.push(Interpolation {
+                        braces: 0,
+                        start: self.start,
+                    });
+                    return Ok(());
+                }
+                c => value.push(c),
+            }
+        }
+
+        self.add_literal_token(TokenType::String, value);
+
+        Ok(())
+    }
+
+    /// A `${}` with nothing inside, which is reported and then scanned as if it weren't there so
+    /// that the parser doesn't take the rest of the string as the expression. Returns the contents
+    /// of the string before the `${`.
+    fn empty_interpolation(&mut self) -> Option<String> {
+        // nothing was scanned since the `${`
+        let token = self
+            .tokens
+            .pop_if(|token| token.token_type == TokenType::Interpolation)?;
+
+        self.errors.push(ScannerError::EmptyInterpolation {
+            span: Span::new(self.file, token.span.end - 2, self.current),
+        });
+        self.start = token.span.start;
+
+        Some(token.lexeme)
+    }
+
+    /// Apply the escape sequence after a `\`. Invalid escapes are reported and left out of the
+    /// string so that scanning can carry on.
+    fn escape(&mut self) -> Option<char> {
+        // the backslash has already been consumed
+        let start = self.current - 1;
+
+        let escaped = match self.advance()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '$' => '$',
+            'u' => return self.unicode_escape(start),
+            escape => {
+                self.errors.push(ScannerError::UnknownEscape {
+                    escape,
+                    span: self.span_from(start),
+                });
+                return None;
+            }
+        };
+
+        Some(escaped)
+    }
+
+    /// `\u{...}` with 1 to 6 hex digits of a unicode code point
+    fn unicode_escape(&mut self, start: usize) -> Option<char> {
+        let mut digits = String::new();
+        let opened = self.chars.peek() == Some(&'{');
+        if opened {
+            self.advance();
+            while let Some(&c) = self.chars.peek()
+                && c.is_ascii_hexdigit()
+            {
+                digits.push(c);
+                self.advance();
+            }
+        }
+        let closed = opened && self.chars.peek() == Some(&'}');
+        if closed {
+            self.advance();
+        }
+
+        let c = match closed && (1..=6).contains(&digits.len()) {
+            true => u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32),
+            false => None,
+        };
+
+        if c.is_none() {
+            self.errors.push(ScannerError::InvalidUnicodeEscape {
+                span: self.span_from(start),
+            });
         }
 
-        if self.chars.peek().is_none() {
-            return Err(ScannerError::UnterminatedString {
+        c
+    }
+
+    /// `r"..."`, or `r#"..."#` with as many `#` as needed to put `"` in the string. Nothing is
+    /// escaped or interpolated.
+    fn handle_raw_string(&mut self) -> ScannerResult<()> {
+        let mut hashes = 0;
+        while self.chars.peek() == Some(&'#') {
+            self.advance();
+            hashes += 1;
+        }
+
+        if self.advance() != Some('"') {
+            return Err(ScannerError::InvalidRawString {
                 span: self.to_span(),
             });
         }
 
-        // consume closing "
-        self.advance();
-        self.add_token(TokenType::String);
+        let mut value = String::new();
+        loop {
+            let Some(c) = self.advance() else {
+                return Err(ScannerError::UnterminatedString {
+                    span: self.to_span(),
+                });
+            };
+
+            if c == '"' {
+                let mut ahead = self.chars.clone();
+                if (0..hashes).all(|_| ahead.next() == Some('#')) {
+                    for _ in 0..hashes {
+                        self.advance();
+                    }
+                    break;
+                }
+            }
+
+            value.push(c);
+        }
+
+        self.add_literal_token(TokenType::String, value);
 
         Ok(())
     }
 
-    fn handle_number(&mut self) {
-        // consume whole number
-        self.consume_digits();
+    /// String and number tokens hold their value rather than their source text
+    fn add_literal_token(&mut self, token_type: TokenType, value: String) {
+        let token = Token::new(token_type, value, self.to_span());
+        self.tokens.push(token);
+    }
+
+    /// Number tokens hold their value in decimal so that the parser only deals with one format.
+    /// Malformed numbers are reported and still produce a token so that parsing can carry on.
+    fn handle_number(&mut self, first: char) {
+        let value = match (first, self.chars.peek()) {
+            ('0', Some('x' | 'X')) => self.prefixed_number(16, "hexadecimal"),
+            ('0', Some('b' | 'B')) => self.prefixed_number(2, "binary"),
+            _ => self.decimal_number(first),
+        };
+
+        self.add_literal_token(TokenType::Number, value);
+    }
+
+    /// `123`, `1_000.5` or `1.5e-9`
+    fn decimal_number(&mut self, first: char) -> String {
+        let mut number = self.consume_digits(10, first.to_string());
 
         // create a second iterator to look farther
         let mut chars2 = self.chars.clone();
@@ -209,29 +463,386 @@ impl<'a> Scanner<'a> {
             (Some(c), Some(next)) if *c == '.' && next.is_ascii_digit() => {
                 // consume '.'
                 self.advance();
+                number.push('.');
 
                 // consume fractional
-                self.consume_digits();
+                number = self.consume_digits(10, number);
             }
             _ => (),
         };
 
-        self.add_token(TokenType::Number);
+        if let Some('e' | 'E') = self.chars.peek() {
+            let start = self.current;
+            self.advance();
+
+            let sign = match self.chars.peek() {
+                Some(&sign @ ('+' | '-')) => {
+                    self.advance();
+                    sign.to_string()
+                }
+                _ => String::new(),
+            };
+
+            let exponent = self.consume_digits(10, String::new());
+            if exponent.is_empty() {
+                self.errors.push(ScannerError::MissingExponent {
+                    span: self.span_from(start),
+                });
+            } else {
+                number = format!("{number}e{sign}{exponent}");
+            }
+        }
+
+        number
+    }
+
+    /// `0xFF` or `0b1010`, after the `0`
+    fn prefixed_number(&mut self, radix: u32, base: &'static str) -> String {
+        // consume the `x` or `b`
+        self.advance();
+
+        let digits = self.consume_digits(radix, String::new());
+        if digits.is_empty() {
+            self.errors.push(ScannerError::MissingDigits {
+                base,
+                span: self.to_span(),
+            });
+        }
+
+        // digits of a larger base, or letters, straight after the number -e.g. `0b102`
+        if let Some(&digit) = self.chars.peek()
+            && Scanner::is_identifier_continue(digit)
+        {
+            let start = self.current;
+            while matches!(self.chars.peek(), Some(&c) if Scanner::is_identifier_continue(c)) {
+                self.advance();
+            }
+            self.errors.push(ScannerError::InvalidDigit {
+                digit,
+                base,
+                span: self.span_from(start),
+            });
+        }
+
+        // folded as a float so that numbers too large for an integer are still approximated
+        let value = digits.chars().fold(0.0, |value, digit| {
+            value * f64::from(radix) + f64::from(digit.to_digit(radix).unwrap_or(0))
+        });
+
+        value.to_string()
     }
 
     fn handle_identifier_and_keywords(&mut self) {
         // extract the entire identifier before categorising it. See maximal munch
-        while matches!(self.chars.peek(), Some(c) if Scanner::is_identifier(c)) {
+        while matches!(self.chars.peek(), Some(&c) if Scanner::is_identifier_continue(c)) {
             self.advance();
         }
 
         let identifier = &self.source[self.start..self.current];
 
         // Convert to keyword or identifier
-        self.add_token(TokenType::to_identifier(identifier));
+        let token_type = match TokenType::to_identifier(identifier) {
+            // valid names in the book
+            TokenType::Do | TokenType::Loop if !self.config.extra_loops => TokenType::Identifier,
+            token_type => token_type,
+        };
+        self.add_token(token_type);
+    }
+
+    /// Identifiers follow Unicode's XID rules, like Rust's, with `_` also allowed at the start
+    fn is_identifier_start(c: char) -> bool {
+        unicode_ident::is_xid_start(c) || c == '_'
+    }
+
+    fn is_identifier_continue(c: char) -> bool {
+        unicode_ident::is_xid_continue(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(src: &str) -> Vec<Token> {
+        let mut scanner = Scanner::new(src, FileId::default(), LoxConfig::default());
+        let (tokens, errors) = scanner.scan_tokens();
+        assert!(errors.is_empty(), "{errors:?}");
+        tokens.clone()
+    }
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(FileId::default(), start, end)
+    }
+
+    #[test]
+    fn token_spans() {
+        let tokens = scan("var abc >= 12.5;");
+        let spans: Vec<Span> = tokens.iter().map(|token| token.span).collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                span(0, 3),
+                span(4, 7),
+                span(8, 10),
+                span(11, 15),
+                span(15, 16),
+                span(16, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_after_comments_and_multiline_strings() {
+        let tokens = scan("// comment\n\"a\nb\" x");
+
+        assert_eq!(tokens[0].span, span(11, 16));
+        assert_eq!(tokens[1].span, span(17, 18));
     }
 
-    fn is_identifier(c: &char) -> bool {
-        c.is_ascii_alphanumeric() || *c == '_'
+    #[test]
+    fn unterminated_string_span() {
+        let mut scanner = Scanner::new("x \"abc", FileId::default(), LoxConfig::default());
+        let (_, errors) = scanner.scan_tokens();
+
+        match errors.as_slice() {
+            [ScannerError::UnterminatedString { span: s }] => {
+                assert_eq!(*s, span(2, 6))
+            }
+            other => panic!("expected an unterminated string, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn string_escapes() {
+        let tokens = scan(r#""a\tb\n\\ \" \$ \u{48}\u{1F600}""#);
+        assert_eq!(tokens[0].token_type, TokenType::String);
+        assert_eq!(tokens[0].lexeme, "a\tb\n\\ \" $ H😀");
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        let mut scanner = Scanner::new(
+            r#""\q \u{D800} \u{}" x"#,
+            FileId::default(),
+            LoxConfig::default(),
+        );
+        let (tokens, errors) = scanner.scan_tokens();
+
+        // the string still scans so that later tokens are not affected
+        assert_eq!(tokens[0].lexeme, "  ");
+        assert_eq!(tokens[1].lexeme, "x");
+        match errors.as_slice() {
+            [
+                ScannerError::UnknownEscape {
+                    escape: 'q',
+                    span: q,
+                },
+                ScannerError::InvalidUnicodeEscape { .. },
+                ScannerError::InvalidUnicodeEscape { .. },
+            ] => assert_eq!(*q, span(1, 3)),
+            other => panic!("expected escape errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn raw_strings() {
+        let tokens = scan(r##"r"a\n${b}" r#"say "hi""#"##);
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(lexemes, [r"a\n${b}", r#"say "hi""#, ""]);
+    }
+
+    #[test]
+    fn book_strings_are_literal() {
+        let mut scanner = Scanner::new(
+            r#""C:\path\n" "a\tb ${c}" r"d""#,
+            FileId::default(),
+            LoxConfig::book(),
+        );
+        let (tokens, errors) = scanner.scan_tokens();
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(lexemes, [r"C:\path\n", r"a\tb ${c}", "r", "d", ""]);
+    }
+
+    #[test]
+    fn interpolation_tokens() {
+        let tokens = scan(r#""a ${ {"b": c}["b"] } d""#);
+        let types: Vec<TokenType> = tokens
+            .iter()
+            .map(|token| token.token_type.clone())
+            .collect();
+
+        assert_eq!(
+            types,
+            [
+                TokenType::Interpolation,
+                TokenType::LeftBrace,
+                TokenType::String,
+                TokenType::Colon,
+                TokenType::Identifier,
+                TokenType::RightBrace,
+                TokenType::LeftBracket,
+                TokenType::String,
+                TokenType::RightBracket,
+                TokenType::String,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(tokens[0].lexeme, "a ");
+        assert_eq!(tokens[9].lexeme, " d");
+    }
+
+    #[test]
+    fn empty_interpolation() {
+        let mut scanner = Scanner::new(
+            r#""a ${b} ${ } c""#,
+            FileId::default(),
+            LoxConfig::default(),
+        );
+        let (tokens, errors) = scanner.scan_tokens();
+
+        // the empty `${}` is left out of the string
+        assert_eq!(tokens[2].token_type, TokenType::String);
+        assert_eq!(tokens[2].lexeme, "  c");
+        assert_eq!(tokens[2].span, span(6, 15));
+        match errors.as_slice() {
+            [ScannerError::EmptyInterpolation { span: s }] => assert_eq!(*s, span(8, 12)),
+            other => panic!("expected an empty interpolation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn number_literals() {
+        let tokens = scan("12.5 0xFF 0B1010 1_000_000 1e-9 2.5E+3 0x1_0");
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(
+            lexemes,
+            ["12.5", "255", "10", "1000000", "1e-9", "2.5e+3", "16", ""]
+        );
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        let mut scanner = Scanner::new(
+            "0x; 0b102; 1e; 1_; 1__0; 0x_1;",
+            FileId::default(),
+            LoxConfig::default(),
+        );
+        let (tokens, errors) = scanner.scan_tokens();
+
+        // every literal still produces a number so that the parser isn't thrown off
+        let numbers = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Number)
+            .count();
+        assert_eq!(numbers, 6);
+
+        let spans: Vec<(&str, Span)> = errors
+            .iter()
+            .map(|error| match error {
+                ScannerError::MissingDigits { .. } => ("digits", error.span()),
+                ScannerError::InvalidDigit { digit: '2', .. } => ("digit", error.span()),
+                ScannerError::MissingExponent { .. } => ("exponent", error.span()),
+                ScannerError::MisplacedSeparator { .. } => ("separator", error.span()),
+                other => panic!("unexpected error {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("digits", span(0, 2)),
+                ("digit", span(8, 9)),
+                ("exponent", span(12, 13)),
+                ("separator", span(16, 17)),
+                ("separator", span(20, 21)),
+                ("separator", span(27, 28)),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_block_comments() {
+        let tokens = scan("a /* one /* two\n */ still\n comment */ b");
+
+        assert_eq!(tokens[1].lexeme, "b");
+        assert_eq!(tokens[1].span, span(38, 39));
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let mut scanner = Scanner::new("a\n  /* /* */ b", FileId::default(), LoxConfig::default());
+        let (_, errors) = scanner.scan_tokens();
+
+        match errors.as_slice() {
+            [ScannerError::UnterminatedComment { span: s }] => {
+                assert_eq!(*s, span(4, 6))
+            }
+            other => panic!("expected an unterminated comment, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn multi_byte_characters() {
+        let tokens = scan("/* ✓ */ \"héllo 😀\" // ünïcode\nx");
+
+        assert_eq!(tokens[0].lexeme, "héllo 😀");
+        assert_eq!(tokens[0].span, span(10, 23));
+        assert_eq!(tokens[1].lexeme, "x");
+        assert_eq!(tokens[1].span, span(37, 38));
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let tokens = scan("var π = 3; café_2 _über 名前");
+        let identifiers: Vec<&str> = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Identifier)
+            .map(|token| token.lexeme.as_str())
+            .collect();
+
+        assert_eq!(identifiers, ["π", "café_2", "_über", "名前"]);
+        assert_eq!(tokens[5].span, span(12, 19));
+    }
+
+    #[test]
+    fn loop_keywords_need_the_extended_dialect() {
+        let types = |config| {
+            let mut scanner = Scanner::new("do loop", FileId::default(), config);
+            let (tokens, _) = scanner.scan_tokens();
+            tokens
+                .iter()
+                .map(|token| token.token_type.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            types(LoxConfig::extended()),
+            [TokenType::Do, TokenType::Loop, TokenType::Eof]
+        );
+        assert_eq!(
+            types(LoxConfig::book()),
+            [TokenType::Identifier, TokenType::Identifier, TokenType::Eof]
+        );
+    }
+
+    #[test]
+    fn symbols_are_not_identifiers() {
+        let mut scanner = Scanner::new("a 😀 b", FileId::default(), LoxConfig::default());
+        let (tokens, errors) = scanner.scan_tokens();
+
+        assert_eq!(tokens[1].lexeme, "b");
+        match errors.as_slice() {
+            [
+                ScannerError::UnknownToken {
+                    token: '😀',
+                    span: s,
+                },
+            ] => {
+                assert_eq!(*s, span(2, 6))
+            }
+            other => panic!("expected an unknown token, got {other:?}"),
+        }
     }
 }
@@ -0,0 +1,156 @@
+/// Identifies a source in a [`SourceMap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FileId(usize);
+
+/// A line and column in a source, both counted from 1. Columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Location {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Location {
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+
+/// A range of bytes in one source. `start` is inclusive and `end` is exclusive.
+///
+/// Spans only hold offsets so that they stay small. Use [`SourceMap`] to turn them into lines and
+/// columns.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    /// An empty span at `offset`
+    pub fn at(file: FileId, offset: usize) -> Self {
+        Self::new(file, offset, offset)
+    }
+
+    /// A span that covers both `self` and `other`, which must come after `self` in the same source
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.file, self.start, other.end)
+    }
+
+    /// An empty span right after `self` -e.g. where a missing `;` should go
+    pub fn after(self) -> Self {
+        Self::at(self.file, self.end)
+    }
+}
+
+#[derive(Debug)]
+struct SourceFile {
+    name: String,
+    source: String,
+
+    /// Byte offset where each line starts
+    line_starts: Vec<usize>,
+}
+
+/// Every source that has been run, so that spans can be resolved long after they were scanned
+/// -e.g. a runtime error in a function declared by an earlier REPL line
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        let source = source.into();
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        self.files.push(SourceFile {
+            name: name.into(),
+            source,
+            line_starts,
+        });
+
+        FileId(self.files.len() - 1)
+    }
+
+    // File ids are only handed out by `add`, so looking them up can't fail
+    fn file(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0]
+    }
+
+    pub fn name(&self, file: FileId) -> &str {
+        &self.file(file).name
+    }
+
+    pub fn source(&self, file: FileId) -> &str {
+        &self.file(file).source
+    }
+
+    /// The location of the byte at `offset`
+    pub fn location(&self, file: FileId, offset: usize) -> Location {
+        let file = self.file(file);
+        let line = file.line_starts.partition_point(|&start| start <= offset);
+        let line_start = file.line_starts[line - 1];
+        let col = file.source[line_start..offset].chars().count() + 1;
+
+        Location::new(line, col)
+    }
+
+    pub fn start(&self, span: Span) -> Location {
+        self.location(span.file, span.start)
+    }
+
+    pub fn end(&self, span: Span) -> Location {
+        self.location(span.file, span.end)
+    }
+
+    /// The text of a line, without its line break
+    pub fn line(&self, file: FileId, line: usize) -> Option<&str> {
+        let file = self.file(file);
+        let start = *file.line_starts.get(line.checked_sub(1)?)?;
+        let end = file
+            .line_starts
+            .get(line)
+            .map_or(file.source.len(), |next| next - 1);
+
+        Some(file.source[start..end].trim_end_matches('\r'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations() {
+        let mut sources = SourceMap::default();
+        sources.add("first.lox", "print 1;");
+        let file = sources.add("second.lox", "var π = 1;\r\n\n  x;");
+
+        assert_eq!(sources.name(file), "second.lox");
+        assert_eq!(sources.location(file, 0), Location::new(1, 1));
+        // `π` takes two bytes but a single column
+        assert_eq!(sources.location(file, 7), Location::new(1, 7));
+        assert_eq!(sources.location(file, 13), Location::new(2, 1));
+        assert_eq!(sources.start(Span::new(file, 16, 17)), Location::new(3, 3));
+        assert_eq!(sources.end(Span::new(file, 16, 17)), Location::new(3, 4));
+    }
+
+    #[test]
+    fn lines() {
+        let mut sources = SourceMap::default();
+        let file = sources.add("test.lox", "a;\r\nb;\n");
+
+        assert_eq!(sources.line(file, 1), Some("a;"));
+        assert_eq!(sources.line(file, 2), Some("b;"));
+        assert_eq!(sources.line(file, 3), Some(""));
+        assert_eq!(sources.line(file, 4), None);
+        assert_eq!(sources.line(file, 0), None);
+    }
+}
@@ -10,15 +10,49 @@ pub enum SyntaxError {
 
     #[error("Scanner error: {0}")]
     Scanner(#[from] ScannerError),
+
+    #[error("Resolver error: {0}")]
+    Resolver(#[from] ResolverError),
 }
 
 #[derive(Error, Debug)]
 pub enum ScannerError {
-    #[error("Unknown token: {token} at {}", span.to_location())]
+    #[error("Unknown token: {token}")]
     UnknownToken { token: char, span: Span },
 
-    #[error("Unterminated string at {}", span.to_location())]
+    #[error("Unterminated string")]
     UnterminatedString { span: Span },
+
+    #[error("Unterminated block comment")]
+    UnterminatedComment { span: Span },
+
+    #[error("Unknown escape sequence: \\{escape}")]
+    UnknownEscape { escape: char, span: Span },
+
+    #[error("Invalid unicode escape sequence")]
+    InvalidUnicodeEscape { span: Span },
+
+    #[error("Expected an expression between ${{ and }}")]
+    EmptyInterpolation { span: Span },
+
+    #[error("Invalid raw string")]
+    InvalidRawString { span: Span },
+
+    #[error("Expected digits after the {base} prefix")]
+    MissingDigits { base: &'static str, span: Span },
+
+    #[error("Invalid digit {digit} in {base} number")]
+    InvalidDigit {
+        digit: char,
+        base: &'static str,
+        span: Span,
+    },
+
+    #[error("Expected digits in exponent")]
+    MissingExponent { span: Span },
+
+    #[error("Digit separator must be between two digits")]
+    MisplacedSeparator { span: Span },
 }
 
 #[derive(Error, Debug)]
@@ -27,14 +61,137 @@ pub enum ParserError {
     ExpectedToken {
         token_type: TokenType,
         message: &'static str,
+        span: Span,
     },
 
     #[error("Expected expression")]
-    ExpectedExpression,
+    ExpectedExpression { span: Span },
+
+    /// The input ended before the statement did, so more input could still complete it
+    #[error("Unexpected end of input: {message}")]
+    UnexpectedEof { message: &'static str, span: Span },
 
-    #[error("Invalid number: {} at {}", token.lexeme, token.span.to_location())]
+    #[error("Invalid number: {}", token.lexeme)]
     InvalidNumber { token: Token },
 
-    #[error("Invalid assignment to {token}")]
+    #[error("Invalid assignment target")]
     InvalidAssignmentTarget { token: Token },
+
+    #[error("Missing left-hand operand for `{}`", operator.lexeme)]
+    MissingLeftOperand { operator: Token },
+
+    #[error("Label {} must be followed by a loop", label.lexeme)]
+    LabelWithoutLoop { label: Token },
+
+    #[error("Too many arguments")]
+    TooManyArguments { token: Token },
+}
+
+#[derive(Error, Debug)]
+pub enum ResolverError {
+    #[error("Can't read local variable {} in its own initializer", token.lexeme)]
+    ReadInOwnInitializer { token: Token },
+
+    #[error("Variable {} is already declared in this scope", token.lexeme)]
+    AlreadyDeclared { token: Token },
+
+    #[error("Can't use {} outside of a loop", token.lexeme)]
+    OutsideLoop { token: Token },
+
+    #[error("Can't use {} in book Lox", token.lexeme)]
+    LoopControlDisabled { token: Token },
+
+    #[error("Undefined loop label {}", token.lexeme)]
+    UndefinedLabel { token: Token },
+
+    #[error("Can't return from top-level code")]
+    TopLevelReturn { token: Token },
+
+    #[error("Can't return a value from an initializer")]
+    InitializerReturn { token: Token },
+
+    #[error("Can't use this outside of a class")]
+    ThisOutsideClass { token: Token },
+
+    #[error("Can't use super outside of a class")]
+    SuperOutsideClass { token: Token },
+
+    #[error("Can't use super in a class with no superclass")]
+    SuperWithoutSuperclass { token: Token },
+
+    #[error("A class can't inherit from itself")]
+    InheritsFromItself { token: Token },
+}
+
+impl SyntaxError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Parser(error) => error.span(),
+            Self::Scanner(error) => error.span(),
+            Self::Resolver(error) => error.span(),
+        }
+    }
+
+    /// Whether the error is only because the input ended too soon -e.g. an unclosed block
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self,
+            Self::Parser(ParserError::UnexpectedEof { .. })
+                | Self::Scanner(
+                    ScannerError::UnterminatedString { .. }
+                        | ScannerError::UnterminatedComment { .. }
+                )
+        )
+    }
+}
+
+impl ScannerError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::UnknownToken { span, .. }
+            | Self::UnterminatedString { span }
+            | Self::UnterminatedComment { span }
+            | Self::UnknownEscape { span, .. }
+            | Self::InvalidUnicodeEscape { span }
+            | Self::EmptyInterpolation { span }
+            | Self::InvalidRawString { span }
+            | Self::MissingDigits { span, .. }
+            | Self::InvalidDigit { span, .. }
+            | Self::MissingExponent { span }
+            | Self::MisplacedSeparator { span } => *span,
+        }
+    }
+}
+
+impl ParserError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::ExpectedToken { span, .. }
+            | Self::ExpectedExpression { span }
+            | Self::UnexpectedEof { span, .. } => *span,
+            Self::InvalidNumber { token }
+            | Self::InvalidAssignmentTarget { token }
+            | Self::TooManyArguments { token } => token.span,
+            Self::MissingLeftOperand { operator } => operator.span,
+            Self::LabelWithoutLoop { label } => label.span,
+        }
+    }
+}
+
+impl ResolverError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::ReadInOwnInitializer { token }
+            | Self::AlreadyDeclared { token }
+            | Self::OutsideLoop { token }
+            | Self::LoopControlDisabled { token }
+            | Self::UndefinedLabel { token }
+            | Self::TopLevelReturn { token }
+            | Self::InitializerReturn { token }
+            | Self::ThisOutsideClass { token }
+            | Self::SuperOutsideClass { token }
+            | Self::SuperWithoutSuperclass { token }
+            | Self::InheritsFromItself { token } => token.span,
+        }
+    }
 }
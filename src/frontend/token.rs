@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::Span;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -7,6 +9,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -29,7 +33,12 @@ pub enum TokenType {
 
     // Literals.
     Identifier,
+    /// The lexeme holds the string's contents with escapes already applied
     String,
+    /// The part of a string before an embedded `${...}` expression, up to and including the `${`.
+    /// The lexeme holds the part's contents. The expression's tokens follow, then the rest of the
+    /// string as another `Interpolation` or a final `String`.
+    Interpolation,
     Number,
 
     // Keywords.
@@ -51,37 +60,45 @@ pub enum TokenType {
     While,
     Continue,
     Break,
+    Do,
+    Loop,
 
     // Misc
     Eof,
 }
 
 impl TokenType {
-    pub fn to_identifier(keyword: &str) -> TokenType {
-        match keyword {
-            // Keywords.
-            "and" => TokenType::And,
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "fun" => TokenType::Fun,
-            "for" => TokenType::For,
-            "if" => TokenType::If,
-            "nil" => TokenType::Nil,
-            "or" => TokenType::Or,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "true" => TokenType::True,
-            "var" => TokenType::Var,
-            "while" => TokenType::While,
-            "continue" => TokenType::Continue,
-            "break" => TokenType::Break,
+    /// Every keyword, with the word that spells it. `to_identifier` and the REPL's completion both
+    /// read this.
+    pub const KEYWORDS: [(&str, TokenType); 20] = [
+        ("and", TokenType::And),
+        ("class", TokenType::Class),
+        ("else", TokenType::Else),
+        ("false", TokenType::False),
+        ("fun", TokenType::Fun),
+        ("for", TokenType::For),
+        ("if", TokenType::If),
+        ("nil", TokenType::Nil),
+        ("or", TokenType::Or),
+        ("print", TokenType::Print),
+        ("return", TokenType::Return),
+        ("super", TokenType::Super),
+        ("this", TokenType::This),
+        ("true", TokenType::True),
+        ("var", TokenType::Var),
+        ("while", TokenType::While),
+        ("continue", TokenType::Continue),
+        ("break", TokenType::Break),
+        ("do", TokenType::Do),
+        ("loop", TokenType::Loop),
+    ];
 
+    pub fn to_identifier(keyword: &str) -> TokenType {
+        Self::KEYWORDS
+            .into_iter()
+            .find_map(|(word, token_type)| (word == keyword).then_some(token_type))
             // Not a keyword
-            _ => TokenType::Identifier,
-        }
+            .unwrap_or(TokenType::Identifier)
     }
 }
 
@@ -94,6 +111,8 @@ impl fmt::Display for TokenType {
             TokenType::RightParen => write!(f, "RightParen"),
             TokenType::LeftBrace => write!(f, "LeftBrace"),
             TokenType::RightBrace => write!(f, "RightBrace"),
+            TokenType::LeftBracket => write!(f, "LeftBracket"),
+            TokenType::RightBracket => write!(f, "RightBracket"),
             TokenType::Comma => write!(f, "Comma"),
             TokenType::Dot => write!(f, "Dot"),
             TokenType::Minus => write!(f, "Minus"),
@@ -117,6 +136,7 @@ impl fmt::Display for TokenType {
             // Literals.
             TokenType::Identifier => write!(f, "Identifier"),
             TokenType::String => write!(f, "String"),
+            TokenType::Interpolation => write!(f, "Interpolation"),
             TokenType::Number => write!(f, "Number"),
 
             // Keywords.
@@ -138,6 +158,8 @@ impl fmt::Display for TokenType {
             TokenType::While => write!(f, "While"),
             TokenType::Continue => write!(f, "Continue"),
             TokenType::Break => write!(f, "Break"),
+            TokenType::Do => write!(f, "Do"),
+            TokenType::Loop => write!(f, "Loop"),
 
             // Misc.
             TokenType::Eof => write!(f, "Eof"),
@@ -145,22 +167,6 @@ impl fmt::Display for TokenType {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Span {
-    pub line: usize,
-    pub col: usize,
-}
-
-impl Span {
-    pub fn new(line: usize, col: usize) -> Self {
-        Self { line, col }
-    }
-
-    pub fn to_location(&self) -> String {
-        format!("{}, {}", self.line, self.col)
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
@@ -180,6 +186,23 @@ impl Token {
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, " {}| {}", self.span.to_location(), self.token_type)
+        write!(
+            f,
+            " {}..{}| {}",
+            self.span.start, self.span.end, self.token_type
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords() {
+        for (keyword, token_type) in TokenType::KEYWORDS {
+            assert_eq!(TokenType::to_identifier(keyword), token_type, "{keyword}");
+        }
+        assert_eq!(TokenType::to_identifier("loops"), TokenType::Identifier);
     }
 }
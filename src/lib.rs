@@ -2,8 +2,20 @@ mod ast;
 mod backend;
 mod frontend;
 mod lox;
+mod lox_config;
 mod lox_error;
+mod repl;
+mod reporter;
 
 // re-export current level as a flat package
 pub use lox::*;
+pub use lox_config::*;
 pub use lox_error::*;
+pub use repl::*;
+pub use reporter::*;
+
+// values are returned to embedders by `Lox::run_repl`
+pub use backend::Value;
+
+// embedders run the interpreter on a thread with a stack this big
+pub use backend::STACK_SIZE;
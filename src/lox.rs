@@ -1,36 +1,113 @@
 use crate::{
-    LoxError,
-    backend::Interpreter,
-    frontend::{Parser, Scanner},
+    LoxConfig, LoxError,
+    ast::Stmt,
+    backend::{Interpreter, Value},
+    frontend::{Parser, Resolver, Scanner, SourceMap, SyntaxError, Token},
 };
 
 #[derive(Debug)]
 pub struct Lox {
     interpreter: Interpreter,
+    config: LoxConfig,
+
+    /// Every source that has been run. Functions declared by one source can be called by a later
+    /// one, so errors can point into any of them.
+    sources: SourceMap,
 }
 
 impl Default for Lox {
     fn default() -> Self {
-        Self::new()
+        Self::new(LoxConfig::default())
     }
 }
 
 pub type LoxResult<T> = Result<T, LoxError>;
 
 impl Lox {
-    pub fn new() -> Self {
+    pub fn new(config: LoxConfig) -> Self {
         Self {
-            interpreter: Interpreter::new(),
+            interpreter: Interpreter::new(config),
+            config,
+            sources: SourceMap::default(),
         }
     }
 
-    pub fn run(&mut self, src: &str) -> LoxResult<()> {
-        let mut scanner = Scanner::new(src);
-        let tokens = scanner.scan_tokens()?;
-        let mut parser = Parser::new(tokens);
-        let stmts = parser.parse()?;
+    /// Sources needed to render the spans in errors returned by `run`
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// Run `src`. `name` is shown in diagnostics -e.g. the script's path.
+    ///
+    /// Must be called on a thread with a stack of at least [`STACK_SIZE`](crate::STACK_SIZE)
+    /// bytes. Deep recursion is reported as an error before it uses that much stack, so on a
+    /// smaller stack, like the main thread's, it can abort the whole process instead.
+    pub fn run(&mut self, name: &str, src: &str) -> LoxResult<()> {
+        let stmts = self.parse(name, src, false)?;
+        Resolver::new(self.config).resolve(&stmts)?;
         self.interpreter.interpret(&stmts)?;
 
         Ok(())
     }
+
+    /// Run a REPL entry, which can end with an expression that has no `;`. Returns the value of
+    /// the last statement when it is an expression statement.
+    ///
+    /// Needs a thread with a stack of at least [`STACK_SIZE`](crate::STACK_SIZE) bytes, see `run`.
+    pub fn run_repl(&mut self, name: &str, src: &str) -> LoxResult<Option<Value>> {
+        let stmts = self.parse(name, src, true)?;
+        Resolver::new(self.config).resolve(&stmts)?;
+        Ok(self.interpreter.interpret_with_value(&stmts)?)
+    }
+
+    /// The tokens of `src`, without running it
+    pub(crate) fn tokens(&mut self, name: &str, src: &str) -> LoxResult<Vec<Token>> {
+        let file = self.sources.add(name, src);
+        let mut scanner = Scanner::new(src, file, self.config);
+        let (tokens, errors) = scanner.scan_tokens();
+
+        match errors.is_empty() {
+            true => Ok(tokens.clone()),
+            false => Err(LoxError::Syntax(
+                errors.into_iter().map(Into::into).collect(),
+            )),
+        }
+    }
+
+    /// The statements of a REPL entry, without running them
+    pub(crate) fn ast(&mut self, name: &str, src: &str) -> LoxResult<Vec<Stmt>> {
+        self.parse(name, src, true)
+    }
+
+    /// Every variable in scope, see `Env::scopes`
+    pub(crate) fn scopes(&self) -> Vec<Vec<(String, Option<Value>)>> {
+        self.interpreter.scopes()
+    }
+
+    /// Scan and parse `src`
+    fn parse(&mut self, name: &str, src: &str, trailing_expression: bool) -> LoxResult<Vec<Stmt>> {
+        let file = self.sources.add(name, src);
+        let mut scanner = Scanner::new(src, file, self.config);
+        let (tokens, scanner_errors) = scanner.scan_tokens();
+
+        // parse whatever could be scanned so that all syntax errors are reported together
+        let mut errors: Vec<SyntaxError> = scanner_errors.into_iter().map(Into::into).collect();
+        let mut parser = Parser::new(tokens);
+        if trailing_expression {
+            parser = parser.allow_trailing_expression();
+        }
+        let stmts = match parser.parse() {
+            Ok(stmts) => stmts,
+            Err(parser_errors) => {
+                errors.extend(parser_errors.into_iter().map(Into::into));
+                Vec::new()
+            }
+        };
+
+        if !errors.is_empty() {
+            return Err(LoxError::Syntax(errors));
+        }
+
+        Ok(stmts)
+    }
 }
@@ -0,0 +1,63 @@
+/// Which deviations from the language in Crafting Interpreters are enabled
+///
+/// [`LoxConfig::book`] runs scripts the same way as the reference jlox/clox implementations, while
+/// [`LoxConfig::extended`] is this implementation's own dialect and the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoxConfig {
+    /// `0`, negative numbers and `""` are falsey, not just `nil` and `false`
+    pub extended_truthiness: bool,
+
+    /// `+` concatenates a string with a value of any type, not just another string
+    pub mixed_concatenation: bool,
+
+    /// `break` and `continue` are allowed inside loops
+    pub loop_control: bool,
+
+    /// `do`-`while` and `loop` statements. Otherwise `do` and `loop` are ordinary names.
+    pub extra_loops: bool,
+
+    /// Escapes and `${...}` interpolation in strings, and raw strings. Otherwise strings hold
+    /// exactly what is between the quotes.
+    pub extended_strings: bool,
+
+    /// Reading a variable declared without an initializer is an error instead of `nil`. Off in
+    /// both dialects, since such variables have always read as `nil`.
+    pub strict_uninitialized: bool,
+
+    /// Dividing by zero is an error instead of producing infinity or NaN
+    pub checked_division: bool,
+}
+
+impl LoxConfig {
+    /// The language exactly as described in the book
+    pub fn book() -> Self {
+        Self {
+            extended_truthiness: false,
+            mixed_concatenation: false,
+            loop_control: false,
+            extra_loops: false,
+            extended_strings: false,
+            strict_uninitialized: false,
+            checked_division: false,
+        }
+    }
+
+    /// The extended dialect, with every deviation except `strict_uninitialized` enabled
+    pub fn extended() -> Self {
+        Self {
+            extended_truthiness: true,
+            mixed_concatenation: true,
+            loop_control: true,
+            extra_loops: true,
+            extended_strings: true,
+            strict_uninitialized: false,
+            checked_division: true,
+        }
+    }
+}
+
+impl Default for LoxConfig {
+    fn default() -> Self {
+        Self::extended()
+    }
+}
@@ -1,29 +1,63 @@
 use crate::{
     backend::RuntimeError,
-    frontend::{ParserError, ScannerError, SyntaxError},
+    frontend::{ParserError, ResolverError, ScannerError, SyntaxError},
 };
 use thiserror::Error;
 
+/// Errors only display their message. Their locations need the sources they point into, see
+/// [`crate::Reporter`].
 #[derive(Error, Debug)]
 pub enum LoxError {
-    #[error("Syntax error: {0}")]
-    Syntax(#[from] SyntaxError),
+    /// Every syntax error found in the source, in order
+    #[error("{}", display_syntax_errors(.0))]
+    Syntax(Vec<SyntaxError>),
 
-    #[error("Runtime error: {0}")]
+    #[error("{}", display_runtime_error(.0))]
     Runtime(#[from] RuntimeError),
 }
 
+impl LoxError {
+    /// Whether more input could fix the error, which is how the REPL knows to keep reading lines
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self, Self::Syntax(errors) if errors.iter().all(SyntaxError::is_incomplete))
+    }
+}
+
+fn display_syntax_errors(errors: &[SyntaxError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("Syntax error: {error}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn display_runtime_error(error: &RuntimeError) -> String {
+    format!("Runtime error: {error}")
+}
+
 // -----------------------------------------------------------------------------
 // automatic conversion
 // -----------------------------------------------------------------------------
+impl From<SyntaxError> for LoxError {
+    fn from(value: SyntaxError) -> Self {
+        Self::Syntax(vec![value])
+    }
+}
+
 impl From<ParserError> for LoxError {
     fn from(value: ParserError) -> Self {
-        Self::Syntax(value.into())
+        SyntaxError::from(value).into()
     }
 }
 
 impl From<ScannerError> for LoxError {
     fn from(value: ScannerError) -> Self {
-        Self::Syntax(value.into())
+        SyntaxError::from(value).into()
+    }
+}
+
+impl From<Vec<ResolverError>> for LoxError {
+    fn from(value: Vec<ResolverError>) -> Self {
+        Self::Syntax(value.into_iter().map(Into::into).collect())
     }
 }
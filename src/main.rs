@@ -1,71 +1,53 @@
-use std::{
-    env, fs,
-    io::{self, Write},
-    process,
-};
+use std::{env, fs, process, thread};
 
-use lox::Lox;
+use lox::{Lox, LoxConfig, Repl, Reporter, STACK_SIZE};
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    match args.len() {
-        1 => run_prompt(),
-        2 => run_file(&args[1]),
-        _ => {
-            println!("Usage: lox [script]");
-            process::exit(54)
-        }
+    // deep recursion in a script needs a bigger stack than the main thread has
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("Failed to start the interpreter thread");
+
+    if interpreter.join().is_err() {
+        process::exit(70);
     }
 }
 
-fn run_prompt() {
-    println!("Lox REPL");
-    let mut lox = Lox::new();
-
-    let mut buf = String::new();
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
-    loop {
-        print!("> ");
-        stdout.flush().unwrap();
-
-        match stdin.read_line(&mut buf) {
-            Ok(0) => break, // EOL
-            Ok(_) => {
-                let source = &buf.trim();
-
-                // no-op on  whitespace
-                if source.is_empty() {
-                    continue;
-                }
-
-                // TODO: refactor this entire file
-                if let Err(e) = lox.run(source) {
-                    eprintln!("{e}");
-                }
-            }
-            Err(e) => {
-                eprintln!("{e}");
-                process::exit(65)
-            }
-        };
+fn run() {
+    let mut config = LoxConfig::default();
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.strip_prefix("--dialect=") {
+            Some("book") => config = LoxConfig::book(),
+            Some("extended") => config = LoxConfig::extended(),
+            Some(_) => usage(),
+            None => paths.push(arg),
+        }
+    }
 
-        // prevent appending contents on next pass
-        buf.clear();
+    match paths.as_slice() {
+        [] => Repl::new(config).run(),
+        [path] => run_file(path, config),
+        _ => usage(),
     }
 }
 
-fn run_file(path: &str) {
-    let mut lox = Lox::new();
+fn usage() -> ! {
+    println!("Usage: lox [--dialect=book|extended] [script]");
+    process::exit(54)
+}
+
+fn run_file(path: &str, config: LoxConfig) {
+    let mut lox = Lox::new(config);
 
     let Ok(src) = fs::read_to_string(path) else {
         eprintln!("Failed to read {path}");
         process::exit(65)
     };
 
-    // TODO: implement Reporter::report_error;
-    if let Err(error) = lox.run(&src) {
-        eprintln!("{error:#?}");
+    if let Err(error) = lox.run(path, &src) {
+        Reporter::for_stderr().report_error(&error, lox.sources());
         process::exit(65);
     }
     // Chapter 7 adds something along the lines of `had_runtime_error` => exit(70)
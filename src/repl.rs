@@ -0,0 +1,264 @@
+mod input;
+
+use std::{fs, ops::ControlFlow, process, time::Instant};
+
+use crate::{Lox, LoxConfig, LoxError, LoxResult, Reporter, Value, ast::Stmt};
+use input::{Input, Line};
+
+/// Name of the REPL's sources in diagnostics
+const SOURCE_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+Enter Lox statements to run them, or an expression to print its value.
+
+Commands:
+  :help          show this message
+  :vars          show every variable in scope with its value
+  :reset         forget every variable and start a fresh session
+  :load <file>   run a file in the current session
+  :tokens <src>  show the tokens of <src> without running it
+  :ast <src>     show the syntax tree of <src> without running it
+  :time <src>    run <src> and show how long it took
+  :quit          leave the REPL";
+
+/// A line starting with `:`, which controls the session instead of being run as Lox
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    Help,
+    Vars,
+    Reset,
+    Load(&'a str),
+    Tokens(&'a str),
+    Ast(&'a str),
+    Time(&'a str),
+    Quit,
+}
+
+/// Why a `:` line isn't a command
+#[derive(Debug, PartialEq)]
+enum CommandError<'a> {
+    Unknown(&'a str),
+    MissingArgument(&'static str),
+}
+
+impl<'a> Command<'a> {
+    /// Parse a line that starts with `:`
+    fn parse(line: &'a str) -> Result<Self, CommandError<'a>> {
+        let line = line.trim();
+        let (name, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let argument = argument.trim();
+
+        let with_argument = |command: fn(&'a str) -> Self, usage| match argument.is_empty() {
+            true => Err(CommandError::MissingArgument(usage)),
+            false => Ok(command(argument)),
+        };
+
+        match name {
+            ":help" => Ok(Self::Help),
+            ":vars" => Ok(Self::Vars),
+            ":reset" => Ok(Self::Reset),
+            ":load" => with_argument(Self::Load, ":load <file>"),
+            ":tokens" => with_argument(Self::Tokens, ":tokens <src>"),
+            ":ast" => with_argument(Self::Ast, ":ast <src>"),
+            ":time" => with_argument(Self::Time, ":time <src>"),
+            ":quit" => Ok(Self::Quit),
+            name => Err(CommandError::Unknown(name)),
+        }
+    }
+}
+
+/// The interactive prompt, started by running `lox` without a script
+pub struct Repl {
+    lox: Lox,
+    config: LoxConfig,
+    reporter: Reporter,
+}
+
+impl Repl {
+    pub fn new(config: LoxConfig) -> Self {
+        Self {
+            lox: Lox::new(config),
+            config,
+            reporter: Reporter::for_stderr(),
+        }
+    }
+
+    /// Read and run lines from stdin until `:quit` or EOF. Like `Lox::run`, needs a thread with a
+    /// stack of at least [`STACK_SIZE`](crate::STACK_SIZE) bytes.
+    pub fn run(&mut self) {
+        println!("Lox REPL, enter :help for help");
+        let mut input = Input::new(self.config);
+
+        // lines of the statement being entered, which can span several lines -e.g. a block
+        let mut source = String::new();
+        loop {
+            // show that the previous lines are still waiting for the rest of the statement
+            let prompt = if source.is_empty() { "> " } else { "... " };
+            let (buf, eof) = match input.read_line(prompt, self.names()) {
+                Ok(Line::Text(line)) => (line + "\n", false),
+                Ok(Line::Eof) => (String::new(), true),
+                Ok(Line::Interrupted) => {
+                    source.clear();
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    input.save_history();
+                    process::exit(65)
+                }
+            };
+
+            // commands can't be part of a statement, so they are only checked on the first line
+            if source.is_empty() && buf.trim_start().starts_with(':') {
+                match self.command(&buf) {
+                    ControlFlow::Continue(()) => continue,
+                    ControlFlow::Break(()) => break,
+                }
+            }
+
+            // a blank line, or EOF, runs whatever has been entered so that its errors are shown
+            let submit = eof || buf.trim().is_empty();
+            if submit && source.trim().is_empty() {
+                if eof {
+                    break;
+                }
+                source.clear();
+                continue;
+            }
+            source.push_str(&buf);
+
+            match self.lox.run_repl(SOURCE_NAME, source.trim_end()) {
+                Err(e) if e.is_incomplete() && !submit => continue,
+                result => self.show(result),
+            }
+            source.clear();
+
+            if eof {
+                break;
+            }
+        }
+
+        input.save_history();
+    }
+
+    /// Every variable in scope, for tab completion
+    fn names(&self) -> Vec<String> {
+        self.lox
+            .scopes()
+            .into_iter()
+            .flatten()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Run a `:` line. Breaks when the REPL should exit.
+    fn command(&mut self, line: &str) -> ControlFlow<()> {
+        let command = match Command::parse(line) {
+            Ok(command) => command,
+            Err(CommandError::Unknown(name)) => {
+                eprintln!("Unknown command {name}, enter :help to see the commands");
+                return ControlFlow::Continue(());
+            }
+            Err(CommandError::MissingArgument(usage)) => {
+                eprintln!("Usage: {usage}");
+                return ControlFlow::Continue(());
+            }
+        };
+
+        match command {
+            Command::Help => println!("{HELP}"),
+            Command::Vars => self.vars(),
+            Command::Reset => {
+                self.lox = Lox::new(self.config);
+                println!("Session reset");
+            }
+            Command::Load(path) => match fs::read_to_string(path) {
+                Ok(src) => {
+                    let result = self.lox.run(path, &src).map(|()| None);
+                    self.show(result);
+                }
+                Err(e) => eprintln!("Failed to read {path}: {e}"),
+            },
+            Command::Tokens(src) => match self.lox.tokens(SOURCE_NAME, src) {
+                Ok(tokens) => {
+                    for token in tokens {
+                        let location = self.lox.sources().start(token.span);
+                        println!(
+                            "{}:{} {} {:?}",
+                            location.line, location.col, token.token_type, token.lexeme
+                        );
+                    }
+                }
+                Err(e) => self.report(&e),
+            },
+            Command::Ast(src) => match self.lox.ast(SOURCE_NAME, src) {
+                Ok(stmts) => {
+                    for stmt in &stmts {
+                        println!("{}", Stmt::print(stmt));
+                    }
+                }
+                Err(e) => self.report(&e),
+            },
+            Command::Time(src) => {
+                let start = Instant::now();
+                let result = self.lox.run_repl(SOURCE_NAME, src);
+                let elapsed = start.elapsed();
+                self.show(result);
+                println!("Took {elapsed:?}");
+            }
+            Command::Quit => return ControlFlow::Break(()),
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Print every scope, innermost first
+    fn vars(&self) {
+        let scopes = self.lox.scopes();
+        let last = scopes.len() - 1;
+        for (i, scope) in scopes.iter().enumerate() {
+            match i == last {
+                true => println!("globals:"),
+                false => println!("scope {i}:"),
+            }
+            if scope.is_empty() {
+                println!("  (empty)");
+            }
+            for (name, value) in scope {
+                match value {
+                    Some(value) => println!("  {name} = {}", value.repr()),
+                    None => println!("  {name} (unassigned)"),
+                }
+            }
+        }
+    }
+
+    /// Print the value of an expression, or report the error
+    fn show(&self, result: LoxResult<Option<Value>>) {
+        match result {
+            Ok(Some(value)) => println!("{}", value.repr()),
+            Ok(None) => (),
+            Err(e) => self.report(&e),
+        }
+    }
+
+    fn report(&self, error: &LoxError) {
+        self.reporter.report_error(error, self.lox.sources());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert_eq!(Command::parse(":vars\n"), Ok(Command::Vars));
+        assert_eq!(Command::parse(":ast  1 + 2 "), Ok(Command::Ast("1 + 2")));
+        assert_eq!(
+            Command::parse(":load"),
+            Err(CommandError::MissingArgument(":load <file>"))
+        );
+        assert_eq!(Command::parse(":exit"), Err(CommandError::Unknown(":exit")));
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+};
+
+use rustyline::{
+    Context, Editor, Helper, completion::Completer, error::ReadlineError, highlight::Highlighter,
+    hint::Hinter, history::FileHistory, validate::Validator,
+};
+
+use crate::{LoxConfig, frontend::TokenType};
+
+/// What reading a line produced
+pub enum Line {
+    /// A line, without its line break
+    Text(String),
+
+    /// Ctrl-C, which abandons the statement being entered
+    Interrupted,
+
+    Eof,
+}
+
+/// Where the REPL reads its lines from
+pub enum Input {
+    /// A terminal, with line editing, history and tab completion
+    Editor {
+        editor: Box<Editor<LoxHelper, FileHistory>>,
+
+        /// Where history is kept between sessions, if there is a data directory
+        history: Option<PathBuf>,
+    },
+
+    /// Anything else -e.g. a script piped into the REPL, read line by line as is
+    Plain(io::Stdin),
+}
+
+impl Input {
+    /// An editor when stdin is a terminal, plain reads otherwise. `config` decides which keywords
+    /// are completed.
+    pub fn new(config: LoxConfig) -> Self {
+        if !io::stdin().is_terminal() {
+            return Self::Plain(io::stdin());
+        }
+
+        let Ok(mut editor) = Editor::new() else {
+            return Self::Plain(io::stdin());
+        };
+        editor.set_helper(Some(LoxHelper::new(config)));
+
+        // there is no history to load on the first run
+        let history = dirs::data_dir().map(|dir| dir.join("lox").join("history"));
+        if let Some(path) = &history {
+            let _ = editor.load_history(path);
+        }
+
+        Self::Editor {
+            editor: Box::new(editor),
+            history,
+        }
+    }
+
+    /// Read a line. `names` are the variables to offer as completions besides the keywords.
+    pub fn read_line(&mut self, prompt: &str, names: Vec<String>) -> io::Result<Line> {
+        match self {
+            Self::Editor { editor, .. } => {
+                if let Some(helper) = editor.helper_mut() {
+                    helper.names = names;
+                }
+
+                match editor.readline(prompt) {
+                    Ok(line) => {
+                        if !line.trim().is_empty() {
+                            let _ = editor.add_history_entry(line.as_str());
+                        }
+                        Ok(Line::Text(line))
+                    }
+                    Err(ReadlineError::Interrupted) => Ok(Line::Interrupted),
+                    Err(ReadlineError::Eof) => Ok(Line::Eof),
+                    Err(ReadlineError::Io(e)) => Err(e),
+                    Err(e) => Err(io::Error::other(e)),
+                }
+            }
+            Self::Plain(stdin) => {
+                print!("{prompt}");
+                io::stdout().flush()?;
+
+                let mut line = String::new();
+                match stdin.read_line(&mut line)? {
+                    0 => Ok(Line::Eof),
+                    _ => {
+                        let len = line.trim_end_matches(['\n', '\r']).len();
+                        line.truncate(len);
+                        Ok(Line::Text(line))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write the history to the data directory so that the next session can use it
+    pub fn save_history(&mut self) {
+        let Self::Editor {
+            editor,
+            history: Some(path),
+        } = self
+        else {
+            return;
+        };
+
+        let saved = match path.parent() {
+            Some(dir) => fs::create_dir_all(dir).map_err(ReadlineError::from),
+            None => Ok(()),
+        }
+        .and_then(|()| editor.save_history(path));
+
+        if let Err(e) = saved {
+            eprintln!("Failed to save history to {}: {e}", path.display());
+        }
+    }
+}
+
+/// Completes keywords and the variables that are in scope
+pub struct LoxHelper {
+    names: Vec<String>,
+
+    /// Keywords that the dialect doesn't have are left out
+    config: LoxConfig,
+}
+
+impl LoxHelper {
+    fn new(config: LoxConfig) -> Self {
+        Self {
+            names: Vec::new(),
+            config,
+        }
+    }
+
+    /// Words that start with `prefix`, sorted and without duplicates
+    fn candidates(&self, prefix: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = TokenType::KEYWORDS
+            .iter()
+            .filter(|(_, token_type)| match token_type {
+                TokenType::Do | TokenType::Loop => self.config.extra_loops,
+                TokenType::Break | TokenType::Continue => self.config.loop_control,
+                _ => true,
+            })
+            .map(|(keyword, _)| *keyword)
+            .chain(self.names.iter().map(String::as_str))
+            .filter(|word| word.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        // the word being typed, which may be empty
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| unicode_ident::is_xid_continue(*c))
+            .last()
+            .map_or(pos, |(i, _)| i);
+
+        Ok((start, self.candidates(&line[start..pos])))
+    }
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_keywords_and_names() {
+        let helper = LoxHelper {
+            names: vec!["printer".to_string(), "count".to_string()],
+            config: LoxConfig::extended(),
+        };
+
+        assert_eq!(helper.candidates("pr"), ["print", "printer"]);
+        assert_eq!(helper.candidates("co"), ["continue", "count"]);
+        assert_eq!(helper.candidates("lo"), ["loop"]);
+        assert!(helper.candidates("zz").is_empty());
+    }
+
+    #[test]
+    fn completes_the_dialects_keywords() {
+        let helper = LoxHelper {
+            names: vec!["count".to_string()],
+            config: LoxConfig::book(),
+        };
+
+        assert_eq!(helper.candidates("co"), ["count"]);
+        assert!(helper.candidates("lo").is_empty());
+        assert!(helper.candidates("d").is_empty());
+        assert!(helper.candidates("b").is_empty());
+        assert_eq!(helper.candidates("wh"), ["while"]);
+    }
+}
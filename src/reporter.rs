@@ -0,0 +1,320 @@
+use std::{
+    env,
+    fmt::Write,
+    io::{self, IsTerminal},
+};
+
+use crate::{
+    LoxError,
+    backend::{EnvironmentError, RuntimeError},
+    frontend::{Location, ParserError, ResolverError, ScannerError, SourceMap, Span, SyntaxError},
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+
+/// Renders errors as diagnostics that point at the offending source
+///
+/// ```text
+/// error: Expected expression
+///  --> script.lox:1:8
+///   |
+/// 1 | print 1 +;
+///   |         ^
+///   = hint: an expression starts with a value, a variable, `(`, `!` or `-`
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Reporter {
+    colour: bool,
+}
+
+/// A single error flattened into what the reporter needs to render it
+struct Diagnostic {
+    message: String,
+    span: Span,
+    hint: Option<&'static str>,
+}
+
+impl Reporter {
+    pub fn new(colour: bool) -> Self {
+        Self { colour }
+    }
+
+    /// A reporter for stderr, with colour only when it is a terminal and `NO_COLOR` is not set
+    pub fn for_stderr() -> Self {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self::new(io::stderr().is_terminal() && !no_color)
+    }
+
+    /// Print every diagnostic in `error` to stderr
+    pub fn report_error(&self, error: &LoxError, sources: &SourceMap) {
+        eprint!("{}", self.render(error, sources));
+    }
+
+    /// Render every diagnostic in `error`, separated by blank lines. `sources` must hold the
+    /// sources that the error's spans point into.
+    pub fn render(&self, error: &LoxError, sources: &SourceMap) -> String {
+        diagnostics(error)
+            .iter()
+            .map(|diagnostic| self.render_diagnostic(diagnostic, sources))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn render_diagnostic(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}: {}",
+            self.paint(BOLD_RED, "error"),
+            self.paint(BOLD, &diagnostic.message)
+        );
+
+        let span = diagnostic.span;
+        let (start, end) = (sources.start(span), sources.end(span));
+        let line_number = start.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let _ = writeln!(
+            out,
+            "{gutter}{} {}:{}:{}",
+            self.paint(BLUE, "-->"),
+            sources.name(span.file),
+            start.line,
+            start.col
+        );
+
+        if let Some(line) = sources.line(span.file, start.line) {
+            let bar = self.paint(BLUE, "|");
+            let _ = writeln!(out, "{gutter} {bar}");
+            let _ = writeln!(out, "{} {bar} {line}", self.paint(BLUE, &line_number));
+            let _ = writeln!(out, "{gutter} {bar} {}", self.underline(line, start, end));
+        }
+
+        if let Some(hint) = diagnostic.hint {
+            let _ = writeln!(out, "{gutter} {} {hint}", self.paint(CYAN, "= hint:"));
+        }
+
+        out
+    }
+
+    /// Carets under the part of `line` from `start` to `end`. Spans over several lines are
+    /// underlined to the end of their first line, and empty spans still get a single caret.
+    fn underline(&self, line: &str, start: Location, end: Location) -> String {
+        let end = if end.line == start.line {
+            end.col.saturating_sub(1)
+        } else {
+            line.chars().count()
+        };
+
+        // keep tabs so the carets line up with the source above them
+        let start = start.col.saturating_sub(1);
+        let padding: String = line
+            .chars()
+            .take(start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(end.saturating_sub(start).max(1));
+
+        format!("{padding}{}", self.paint(BOLD_RED, &carets))
+    }
+
+    fn paint(&self, colour: &str, text: &str) -> String {
+        if self.colour {
+            format!("{colour}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn diagnostics(error: &LoxError) -> Vec<Diagnostic> {
+    match error {
+        LoxError::Syntax(errors) => {
+            let mut errors: Vec<_> = errors.iter().collect();
+            // scanner errors are collected before parser errors, report them in source order instead
+            errors.sort_by_key(|error| {
+                let span = error.span();
+                (span.file, span.start)
+            });
+
+            errors
+                .into_iter()
+                .map(|error| Diagnostic {
+                    message: match error {
+                        SyntaxError::Parser(error) => error.to_string(),
+                        SyntaxError::Scanner(error) => error.to_string(),
+                        SyntaxError::Resolver(error) => error.to_string(),
+                    },
+                    span: error.span(),
+                    hint: syntax_hint(error),
+                })
+                .collect()
+        }
+        LoxError::Runtime(error) => vec![Diagnostic {
+            message: error.to_string(),
+            span: error.span(),
+            hint: runtime_hint(error),
+        }],
+    }
+}
+
+fn syntax_hint(error: &SyntaxError) -> Option<&'static str> {
+    match error {
+        SyntaxError::Scanner(error) => match error {
+            ScannerError::UnknownToken { .. } => Some("this character is not part of Lox"),
+            ScannerError::UnterminatedString { .. } => Some("add a closing `\"`"),
+            ScannerError::UnterminatedComment { .. } => {
+                Some("close it with `*/`, block comments nest so each `/*` needs its own")
+            }
+            ScannerError::UnknownEscape { .. } => {
+                Some("the escapes are \\n \\t \\r \\0 \\\\ \\\" \\$ and \\u{...}")
+            }
+            ScannerError::InvalidUnicodeEscape { .. } => {
+                Some("write 1 to 6 hex digits of a code point -e.g. \\u{1F600}")
+            }
+            ScannerError::EmptyInterpolation { .. } => {
+                Some("write `\\$` for a literal `$`, or remove the `${}`")
+            }
+            ScannerError::InvalidRawString { .. } => {
+                Some("raw strings look like r\"...\" or r#\"...\"#")
+            }
+            ScannerError::MissingDigits { .. } => Some("write a number like 0xFF or 0b1010"),
+            ScannerError::InvalidDigit { .. } => {
+                Some("binary numbers use 0 and 1, hexadecimal numbers use 0-9 and A-F")
+            }
+            ScannerError::MissingExponent { .. } => Some("write an exponent like 1e9 or 1e-9"),
+            ScannerError::MisplacedSeparator { .. } => {
+                Some("`_` can only separate digits -e.g. 1_000_000")
+            }
+        },
+        SyntaxError::Parser(error) => match error {
+            ParserError::ExpectedToken { .. } | ParserError::InvalidNumber { .. } => None,
+            ParserError::UnexpectedEof { .. } => Some("the input ends before the statement does"),
+            ParserError::ExpectedExpression { .. } => {
+                Some("an expression starts with a value, a variable, `(`, `!` or `-`")
+            }
+            ParserError::InvalidAssignmentTarget { .. } => {
+                Some("only variables, properties and list or map elements can be assigned to")
+            }
+            ParserError::MissingLeftOperand { .. } => Some("add an operand before the operator"),
+            ParserError::LabelWithoutLoop { .. } => {
+                Some("only `for`, `while`, `do` and `loop` can be labelled")
+            }
+            ParserError::TooManyArguments { .. } => Some("calls can pass at most 255 arguments"),
+        },
+        SyntaxError::Resolver(error) => match error {
+            ResolverError::ReadInOwnInitializer { .. } => {
+                Some("give the new variable a different name")
+            }
+            ResolverError::AlreadyDeclared { .. } => {
+                Some("drop `var` to assign to the existing variable")
+            }
+            ResolverError::OutsideLoop { .. } => {
+                Some("`break` and `continue` only work inside `while`, `for`, `do` and `loop`")
+            }
+            ResolverError::LoopControlDisabled { .. } => {
+                Some("`break` and `continue` need the extended dialect")
+            }
+            ResolverError::UndefinedLabel { .. } => {
+                Some("a label must name a loop around the statement, in the same function")
+            }
+            ResolverError::TopLevelReturn { .. } => Some("`return` only works inside a function"),
+            ResolverError::InitializerReturn { .. } => {
+                Some("`init` always returns `this`, use a bare `return;`")
+            }
+            ResolverError::ThisOutsideClass { .. } | ResolverError::SuperOutsideClass { .. } => {
+                Some("only methods can refer to their instance")
+            }
+            ResolverError::SuperWithoutSuperclass { .. } => {
+                Some("declare a superclass with `class Name < Superclass`")
+            }
+            ResolverError::InheritsFromItself { .. } => None,
+        },
+    }
+}
+
+fn runtime_hint(error: &RuntimeError) -> Option<&'static str> {
+    match error {
+        RuntimeError::UndefinedProperty { .. } => Some("fields must be set before they are read"),
+        RuntimeError::StackOverflow { .. } => Some("check that the recursion has a base case"),
+        RuntimeError::Environment {
+            source: EnvironmentError::UndefinedVariable { .. },
+            ..
+        } => Some("declare it first with `var`"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lox;
+
+    fn render(source: &str) -> String {
+        let mut lox = Lox::default();
+        let error = lox.run("test.lox", source).unwrap_err();
+        Reporter::new(false).render(&error, lox.sources())
+    }
+
+    #[test]
+    fn points_at_the_span() {
+        let source = "var a = 1;\nprint a +;";
+        assert_eq!(
+            render(source),
+            "error: Expected expression\n \
+             --> test.lox:2:10\n  \
+              |\n\
+             2 | print a +;\n  \
+              |          ^\n  \
+              = hint: an expression starts with a value, a variable, `(`, `!` or `-`\n"
+        );
+    }
+
+    #[test]
+    fn underlines_the_whole_token() {
+        let source = "print missing;";
+        assert_eq!(
+            render(source),
+            "error: Undefined variable: missing\n \
+             --> test.lox:1:7\n  \
+              |\n\
+             1 | print missing;\n  \
+              |       ^^^^^^^\n  \
+              = hint: declare it first with `var`\n"
+        );
+    }
+
+    #[test]
+    fn colour_is_optional() {
+        let source = "print missing;";
+        let mut lox = Lox::default();
+        let error = lox.run("test.lox", source).unwrap_err();
+
+        assert!(!render(source).contains('\x1b'));
+        assert!(
+            Reporter::new(true)
+                .render(&error, lox.sources())
+                .contains(BOLD_RED)
+        );
+    }
+
+    #[test]
+    fn points_into_earlier_sources() {
+        let mut lox = Lox::default();
+        lox.run("lib.lox", "fun half(n) {\n  return n / 0;\n}")
+            .unwrap();
+        let error = lox.run("main.lox", "half(1);").unwrap_err();
+
+        assert_eq!(
+            Reporter::new(false).render(&error, lox.sources()),
+            "error: Invalid arithmetic operation: division by zero\n \
+             --> lib.lox:2:14\n  \
+              |\n\
+             2 |   return n / 0;\n  \
+              |              ^\n"
+        );
+    }
+}